@@ -0,0 +1,145 @@
+use bevy::input::mouse::{AccumulatedMouseMotion, MouseScrollUnit, MouseWheel};
+use bevy::picking::hover::HoverMap;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use super::ecs::components::{ButtonActions, Explorer, GalaxyCamera, Planet};
+use super::ecs::resources::{CameraTarget, LastClick};
+use super::utils::constants::{CAMERA_SMOOTHING, DOUBLE_CLICK_SECONDS, FOCUS_ZOOM, MAX_ZOOM, MIN_ZOOM, PLANET_RAD,
+                              SIDE_MENU_WIDTH, ZOOM_STEP};
+
+/// Drags the galaxy around while the middle or right button is held
+pub(crate) fn pan_camera(
+    buttons: Res<ButtonInput<MouseButton>>,
+    motion: Res<AccumulatedMouseMotion>,
+    camera: Single<(&mut Transform, &Projection), With<GalaxyCamera>>,
+    mut target: ResMut<CameraTarget>
+) {
+    if !buttons.any_pressed([MouseButton::Middle, MouseButton::Right]) || motion.delta == Vec2::ZERO {
+        return;
+    }
+
+    let (mut transform, projection) = camera.into_inner();
+    let Projection::Orthographic(ortho) = projection else {
+        return;
+    };
+
+    // screen y grows downwards, world y upwards
+    transform.translation.x -= motion.delta.x * ortho.scale;
+    transform.translation.y += motion.delta.y * ortho.scale;
+    target.0 = None;
+}
+
+/// Zooms with the mouse wheel, unless the cursor is over
+/// a menu, which scrolls instead
+pub(crate) fn zoom_camera(
+    mut mouse_wheel_reader: MessageReader<MouseWheel>,
+    hover_map: Res<HoverMap>,
+    nodes: Query<(), With<Node>>,
+    mut projection: Single<&mut Projection, With<GalaxyCamera>>,
+    mut target: ResMut<CameraTarget>
+) {
+    let over_ui = hover_map.values().flat_map(|pointer_map| pointer_map.keys()).any(|&e| nodes.contains(e));
+
+    for mouse_wheel in mouse_wheel_reader.read() {
+        if over_ui {
+            continue;
+        }
+
+        let Projection::Orthographic(ortho) = projection.as_mut() else {
+            return;
+        };
+
+        // pixel deltas are roughly 21 times bigger than line ones
+        let lines = match mouse_wheel.unit {
+            MouseScrollUnit::Line => mouse_wheel.y,
+            MouseScrollUnit::Pixel => mouse_wheel.y / 21.
+        };
+
+        ortho.scale = (ortho.scale * ZOOM_STEP.powf(-lines)).clamp(MIN_ZOOM, MAX_ZOOM);
+        target.0 = None;
+    }
+}
+
+/// Frames every planet between the two side menus
+pub(crate) fn camera_action(
+    action_query: Query<(&Interaction, &ButtonActions), (Changed<Interaction>, With<Button>)>,
+    planets: Query<&Transform, With<Planet>>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    mut target: ResMut<CameraTarget>
+) {
+    for (&interaction, action) in &action_query {
+        if interaction == Interaction::Pressed
+            && let ButtonActions::FitGalaxy = action
+        {
+            if planets.is_empty() {
+                continue;
+            }
+
+            let (mut min, mut max) = (Vec2::MAX, Vec2::MIN);
+            for transform in &planets {
+                min = min.min(transform.translation.truncate());
+                max = max.max(transform.translation.truncate());
+            }
+
+            let size = max - min + Vec2::splat(PLANET_RAD * 4.);
+            let visible = Vec2::new((window.width() - 2. * SIDE_MENU_WIDTH).max(100.), window.height());
+            let scale = (size / visible).max_element().clamp(MIN_ZOOM, MAX_ZOOM);
+
+            target.0 = Some(((min + max) / 2., scale));
+        }
+    }
+}
+
+/// Centers and zooms on a planet or explorer clicked twice in a row
+pub(crate) fn focus_on_double_click(
+    click: On<Pointer<Click>>,
+    celestials: Query<&Transform, Or<(With<Planet>, With<Explorer>)>>,
+    time: Res<Time<Real>>,
+    mut last_click: ResMut<LastClick>,
+    mut target: ResMut<CameraTarget>
+) {
+    if click.button != PointerButton::Primary {
+        return;
+    }
+
+    let Ok(transform) = celestials.get(click.entity) else {
+        return;
+    };
+
+    let now = time.elapsed_secs();
+    if last_click.entity == Some(click.entity) && now - last_click.time < DOUBLE_CLICK_SECONDS {
+        target.0 = Some((transform.translation.truncate(), FOCUS_ZOOM));
+        last_click.entity = None;
+    } else {
+        *last_click = LastClick { entity: Some(click.entity), time: now };
+    }
+}
+
+/// Glides the camera towards the [`CameraTarget`]
+pub(crate) fn move_camera(
+    time: Res<Time<Real>>,
+    mut target: ResMut<CameraTarget>,
+    camera: Single<(&mut Transform, &mut Projection), With<GalaxyCamera>>
+) {
+    let Some((center, scale)) = target.0 else {
+        return;
+    };
+
+    let (mut transform, mut projection) = camera.into_inner();
+    let Projection::Orthographic(ortho) = projection.as_mut() else {
+        return;
+    };
+
+    // frame rate independent exponential smoothing
+    let t = 1. - (-CAMERA_SMOOTHING * time.delta_secs()).exp();
+    let position = transform.translation.truncate().lerp(center, t);
+    transform.translation = position.extend(transform.translation.z);
+    ortho.scale += (scale - ortho.scale) * t;
+
+    if position.distance(center) < 0.5 && (ortho.scale - scale).abs() < 0.001 {
+        transform.translation = center.extend(transform.translation.z);
+        ortho.scale = scale;
+        target.0 = None;
+    }
+}
@@ -0,0 +1,171 @@
+use std::f32::consts::TAU;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_tweening::lens::TransformPositionLens;
+use bevy_tweening::{Tween, TweenAnim};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use super::ecs::components::{GalaxyCamera, Impact, PendingSunray, Planet};
+use super::ecs::events::{Celestial, CelestialBody, SunraySent};
+use super::ecs::resources::{GalaxyRegistry, GuiSettings, SimulationSpeed};
+use super::galaxy::planet_transform;
+use super::utils::assets::CelestialAssets;
+use super::utils::constants::{CELESTIAL_RAD, IMPACT_SECONDS, PLANET_RAD};
+
+/// Where sunrays and asteroids enter the screen from
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum CelestialEntry {
+    /// A different point of the screen edge every time
+    #[default]
+    Random,
+    /// Always the same direction from the center of the screen,
+    /// in radians counterclockwise from the right edge
+    Angle(f32)
+}
+
+pub fn move_celestial(
+    event: On<Celestial>,
+    mut commands: Commands,
+    sprites: Res<CelestialAssets>,
+    settings: Res<GuiSettings>,
+    registry: Res<GalaxyRegistry>,
+    planet_query: Query<&Transform, With<Planet>>,
+    camera: Single<(&Transform, &Projection), With<GalaxyCamera>>,
+    window: Single<&Window, With<PrimaryWindow>>,
+    mut pending: Query<(Entity, &ChildOf, &mut PendingSunray)>,
+    // kept apart from the SessionRng, so the effects don't change the choices of the game
+    mut rng: Local<Option<StdRng>>
+) {
+    info!("MOVE_CELESTIAL: EVENT FROM ID {} ", event.planet_id);
+
+    let Some(t) = planet_transform(&registry, &planet_query, event.planet_id) else {
+        return;
+    };
+
+    let (sunray_sprite, ease) = match event.kind {
+        CelestialBody::Sunray => {
+            info!("spawning sunray sprite");
+
+            // the sunray the planet was waiting for has arrived
+            let planet = registry.planet(event.planet_id);
+            if let Some((indicator, _, mut sunray)) =
+                pending.iter_mut().find(|(_, child_of, _)| Some(child_of.parent()) == planet)
+            {
+                sunray.count -= 1;
+                if sunray.count == 0 {
+                    commands.entity(indicator).despawn();
+                }
+            }
+
+            (sprites.handles.0.clone(), EaseFunction::QuadraticOut)
+        }
+        CelestialBody::Asteroid => {
+            info!("spawning asteroid sprite");
+            // speeds up until the crash
+            (sprites.handles.1.clone(), EaseFunction::QuadraticIn)
+        }
+    };
+
+    let angle = match settings.celestial_entry {
+        CelestialEntry::Random => {
+            let rng = rng.get_or_insert_with(|| StdRng::seed_from_u64(settings.seed));
+            rng.random_range(0. ..TAU)
+        }
+        CelestialEntry::Angle(angle) => angle
+    };
+    let start = screen_edge(*camera, &window, angle).extend(2.0);
+
+    let tween = Tween::new(
+        ease,
+        Duration::from_secs_f32(settings.tick_seconds / 2.),
+        TransformPositionLens { start, end: Vec3::new(t.translation.x, t.translation.y, 2.0) }
+    )
+    .with_cycle_completed_event(true);
+
+    commands.spawn((
+        Celestial { kind: event.kind, planet_id: event.planet_id },
+        Sprite { image: sunray_sprite, custom_size: Some(Vec2::splat(CELESTIAL_RAD * 2.)), ..default() },
+        Transform::from_translation(start),
+        TweenAnim::new(tween)
+    ));
+}
+
+/// Point just out of the screen, in the direction of `angle` from its center
+fn screen_edge((transform, projection): (&Transform, &Projection), window: &Window, angle: f32) -> Vec2 {
+    let scale = match projection {
+        Projection::Orthographic(ortho) => ortho.scale,
+        _ => 1.
+    };
+
+    let distance = (window.size().length() / 2. + CELESTIAL_RAD * 2.) * scale;
+    transform.translation.truncate() + Vec2::from_angle(angle) * distance
+}
+
+/// Asteroids burst against the planet, sunrays sink into it
+pub(crate) fn animate_impacts(
+    mut commands: Commands,
+    time: Res<Time>,
+    speed: Res<SimulationSpeed>,
+    mut impacts: Query<(Entity, &mut Impact, &mut Transform, &mut Sprite)>
+) {
+    for (entity, mut impact, mut transform, mut sprite) in &mut impacts {
+        impact.age += time.delta_secs() * **speed;
+        let t = (impact.age / IMPACT_SECONDS).min(1.);
+
+        match impact.kind {
+            CelestialBody::Asteroid => {
+                transform.scale = Vec3::splat(1. + 2. * t);
+                sprite.color = Color::srgba(1., 0.6 - 0.4 * t, 0.2, 1. - t);
+            }
+            CelestialBody::Sunray => {
+                transform.scale = Vec3::splat(1. - t);
+                sprite.color = Color::srgba(1., 1., 0.6, 1. - t);
+            }
+        }
+
+        if t >= 1. {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// Marks a planet that has a sunray on its way
+pub(crate) fn show_pending_sunray(
+    event: On<SunraySent>,
+    mut commands: Commands,
+    sprites: Res<CelestialAssets>,
+    registry: Res<GalaxyRegistry>,
+    mut pending: Query<(&ChildOf, &mut PendingSunray)>
+) {
+    let Some(planet) = registry.planet(event.planet_id) else {
+        return;
+    };
+
+    // several sunrays can be on their way, one mark is enough
+    if let Some((_, mut sunray)) = pending.iter_mut().find(|(child_of, _)| child_of.parent() == planet) {
+        sunray.count += 1;
+        return;
+    }
+
+    commands.entity(planet).with_child((
+        PendingSunray { count: 1 },
+        Sprite {
+            image: sprites.handles.0.clone(),
+            color: Color::srgba(1., 1., 1., 0.7),
+            custom_size: Some(Vec2::splat(CELESTIAL_RAD)),
+            ..default()
+        },
+        Transform::from_xyz(0., PLANET_RAD * 0.9, 0.4),
+        Pickable::IGNORE
+    ));
+}
+
+pub(crate) fn pulse_pending_sunrays(time: Res<Time>, mut pending: Query<&mut Transform, With<PendingSunray>>) {
+    let scale = 1. + 0.25 * (time.elapsed_secs() * 5.).sin();
+    for mut transform in &mut pending {
+        transform.scale = Vec3::splat(scale);
+    }
+}
@@ -1,34 +1,221 @@
+use bevy::color::Color;
 use bevy::ecs::component::Component;
+use bevy::ecs::lifecycle::HookContext;
+use bevy::ecs::query::{Or, With};
+use bevy::ecs::world::DeferredWorld;
+
+use super::super::event_log::LogKind;
+use super::super::stats::StatsChart;
+use super::events::{Celestial, CelestialBody};
+use super::resources::GalaxyRegistry;
 
 // Galaxy-centric components
 #[derive(Component)]
+#[component(on_insert = register_planet, on_replace = unregister_planet)]
 pub(crate) struct Planet {
     pub id: u32
 }
 
 #[derive(Component)]
+#[component(on_insert = register_explorer, on_replace = unregister_explorer)]
 pub(crate) struct Explorer {
     pub id: u32,
     pub current_planet: u32,
-    pub position_offset: (f32, f32)
+    pub position_offset: (f32, f32),
+    /// Colour the sprite is tinted with
+    pub tint: Color
 }
 
 #[derive(Component)]
 pub(crate) struct Edge {
-    pub connects: (u32, u32)
+    pub connects: (u32, u32),
+    /// Drawn as dashes, because one of its planets is a ghost
+    pub dashed: bool
+}
+
+/// Piece of the ring around a planet standing for one of its energy cells
+#[derive(Component)]
+pub(crate) struct EnergySegment {
+    pub index: usize
+}
+
+/// Shown next to a planet that has built a rocket
+#[derive(Component)]
+pub(crate) struct RocketBadge;
+
+/// Sunray or asteroid that has reached its planet
+#[derive(Component)]
+pub(crate) struct Impact {
+    /// Seconds since the arrival
+    pub age: f32,
+    pub kind: CelestialBody
+}
+
+impl Impact {
+    pub fn new(kind: CelestialBody) -> Self { Self { age: 0., kind } }
+}
+
+/// Shown on a planet waiting for sunrays
+#[derive(Component)]
+pub(crate) struct PendingSunray {
+    /// Sunrays sent and not received yet
+    pub count: u32
+}
+
+/// Tells whether a planet or explorer is paused or dead
+#[derive(Component)]
+pub(crate) struct StatusIcon;
+
+/// What is left of a dead planet, when
+/// [`GuiSettings::ghost_planets`](super::resources::GuiSettings) is set
+#[derive(Component)]
+#[component(on_insert = register_ghost, on_replace = unregister_ghost)]
+pub(crate) struct GhostPlanet {
+    pub id: u32
+}
+
+/// Sprite playing its death animation
+#[derive(Component)]
+pub(crate) struct Dying {
+    /// Seconds since the death
+    pub age: f32,
+    pub kind: DeathKind
+}
+
+impl Dying {
+    pub fn new(kind: DeathKind) -> Self { Self { age: 0., kind } }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum DeathKind {
+    /// Blows up and is despawned
+    Planet,
+    /// Flares up, then stays as a ghost
+    Ghost,
+    /// Spins away and is despawned
+    Explorer
+}
+
+// the registry is missing in headless games, which have no sprites at all
+fn register_planet(mut world: DeferredWorld, ctx: HookContext) {
+    let Some(&Planet { id }) = world.get::<Planet>(ctx.entity) else {
+        return;
+    };
+    if let Some(mut registry) = world.get_resource_mut::<GalaxyRegistry>() {
+        registry.planets.insert(id, ctx.entity);
+    }
+}
+
+fn unregister_planet(mut world: DeferredWorld, ctx: HookContext) {
+    let Some(&Planet { id }) = world.get::<Planet>(ctx.entity) else {
+        return;
+    };
+    if let Some(mut registry) = world.get_resource_mut::<GalaxyRegistry>()
+        && registry.planet(id) == Some(ctx.entity)
+    {
+        registry.planets.remove(&id);
+    }
+}
+
+fn register_ghost(mut world: DeferredWorld, ctx: HookContext) {
+    let Some(&GhostPlanet { id }) = world.get::<GhostPlanet>(ctx.entity) else {
+        return;
+    };
+    if let Some(mut registry) = world.get_resource_mut::<GalaxyRegistry>() {
+        registry.ghosts.insert(id, ctx.entity);
+    }
+}
+
+fn unregister_ghost(mut world: DeferredWorld, ctx: HookContext) {
+    let Some(&GhostPlanet { id }) = world.get::<GhostPlanet>(ctx.entity) else {
+        return;
+    };
+    if let Some(mut registry) = world.get_resource_mut::<GalaxyRegistry>()
+        && registry.ghost(id) == Some(ctx.entity)
+    {
+        registry.ghosts.remove(&id);
+    }
+}
+
+fn register_explorer(mut world: DeferredWorld, ctx: HookContext) {
+    let Some(id) = world.get::<Explorer>(ctx.entity).map(|e| e.id) else {
+        return;
+    };
+    if let Some(mut registry) = world.get_resource_mut::<GalaxyRegistry>() {
+        registry.explorers.insert(id, ctx.entity);
+    }
+}
+
+fn unregister_explorer(mut world: DeferredWorld, ctx: HookContext) {
+    let Some(id) = world.get::<Explorer>(ctx.entity).map(|e| e.id) else {
+        return;
+    };
+    if let Some(mut registry) = world.get_resource_mut::<GalaxyRegistry>()
+        && registry.explorer(id) == Some(ctx.entity)
+    {
+        registry.explorers.remove(&id);
+    }
+}
+
+/// Camera looking at the galaxy, moved by the mouse
+#[derive(Component)]
+pub(crate) struct GalaxyCamera;
+
+/// Name of a freshly generated resource,
+/// floating above the explorer that made it
+#[derive(Component)]
+pub(crate) struct ResourcePopup;
+
+/// Fading mark left behind by a travelling explorer
+#[derive(Component)]
+pub(crate) struct TrailDot {
+    /// Seconds since it was dropped
+    pub age: f32
 }
 
+/// Filter matching every sprite that belongs to the galaxy itself,
+/// despawned when the galaxy has to be rebuilt
+pub(crate) type GalaxyEntities = Or<(
+    With<Planet>,
+    With<GhostPlanet>,
+    With<Dying>,
+    With<Explorer>,
+    With<Edge>,
+    With<Celestial>,
+    With<Impact>,
+    With<ResourcePopup>,
+    With<TrailDot>
+)>;
+
 /// Button associated actions
 #[derive(Component)]
 pub enum ButtonActions {
     StartGame,
     StopGame,
+    Resume,
+    Restart,
+    ShowExplorerMessages,
     ManualAsteroid,
     ManualSunray,
     Blind,
     Nuke,
     CreateBasic,
-    CreateComplex
+    CreateComplex,
+    StepBack,
+    StepForward,
+    GoLatest,
+    SpeedDown,
+    SpeedUp,
+    StepOnce,
+    CycleExplorerFilter,
+    CyclePlanetFilter,
+    CycleLayout,
+    FitGalaxy,
+    ToggleLogKind(LogKind),
+    FocusLogSearch,
+    ExportLog,
+    ShowStats,
+    CloseSummary
 }
 
 /// Planet info marker component
@@ -60,9 +247,52 @@ pub enum UiExplorerText {
     ResourceBag
 }
 
-/// Marker component for any loggable action
+/// Shows the current [`SimulationSpeed`](super::resources::SimulationSpeed)
+#[derive(Component)]
+pub struct SpeedLabel;
+
+/// Shows the current [`LayoutKind`](super::super::layout::LayoutKind)
+#[derive(Component)]
+pub struct LayoutLabel;
+
+/// Holds a row per entry of the [`EventLog`](super::resources::EventLog)
+#[derive(Component)]
+pub struct LogList;
+
+/// Entry of the log about a planet or explorer,
+/// clicking it selects them
+#[derive(Component)]
+pub struct LogRow {
+    pub planet: Option<u32>,
+    pub explorer: Option<u32>
+}
+
+/// Root of the statistics dashboard
+#[derive(Component)]
+pub struct StatsPanel;
+
+/// Holds the bars of a chart of the dashboard
+#[derive(Component)]
+pub(crate) struct StatsChartBars(pub StatsChart);
+
+/// Title of a chart of the dashboard, with its latest value
 #[derive(Component)]
-pub struct LogText;
+pub(crate) struct StatsChartLabel(pub StatsChart);
+
+/// Root of the end of game summary
+#[derive(Component)]
+pub struct SummaryOverlay;
+
+/// Body of the end of game summary
+#[derive(Component)]
+pub struct SummaryText;
+
+/// Text of the log filter buttons
+#[derive(Component)]
+pub enum LogFilterLabel {
+    Kind(LogKind),
+    Search
+}
 
 #[derive(Component)]
 pub struct DropdownRoot;
@@ -81,3 +311,28 @@ pub struct DropdownItem {
     pub explorer_id: u32,
     pub planet_id: u32
 }
+
+/// Clickable bar of the timeline, spans every frame of the history
+#[derive(Component)]
+pub struct TimelineTrack;
+
+/// Filled part of the timeline, up to the frame shown
+#[derive(Component)]
+pub struct TimelineFill;
+
+#[derive(Component)]
+pub struct TimelineLabel;
+
+/// Root of the explorer message inspector
+#[derive(Component)]
+pub struct MessagePanel;
+
+#[derive(Component)]
+pub struct MessageList;
+
+/// Text of the message filter buttons
+#[derive(Component)]
+pub enum MessageFilterLabel {
+    Explorer,
+    Planet
+}
@@ -18,6 +18,12 @@ pub(crate) struct Celestial {
     pub planet_id: u32
 }
 
+/// A sunray is on its way to the planet, it will arrive with a [`Celestial`]
+#[derive(Event)]
+pub(crate) struct SunraySent {
+    pub planet_id: u32
+}
+
 #[derive(Event)]
 pub(crate) struct MoveExplorerEvent {
     pub id: u32,
@@ -35,6 +41,12 @@ pub(crate) struct ComplexResEvent {
     pub id: u32,
     pub resource: ComplexResourceType
 }
+/// Rebuilds the galaxy as it was at a frame of the [`GameHistory`](super::resources::GameHistory)
+#[derive(Event)]
+pub(crate) struct JumpToFrame {
+    pub frame: usize
+}
+
 /// UI scrolling event.
 #[derive(EntityEvent, Debug)]
 #[entity_event(propagate, auto_propagate)]
@@ -1,8 +1,19 @@
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
 
 use bevy::prelude::*;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use serde::{Deserialize, Serialize};
 
+use super::super::celestial::CelestialEntry;
+use super::super::event_log::{LogEntry, LogKind};
+use super::super::export::TickSummary;
+use super::super::layout::LayoutKind;
+use super::super::replay::RecordedTick;
+use super::super::summary::DeathCause;
 use super::super::types::{ExplorerInfoMap, PlanetInfoMap, Status};
+use super::events::CelestialBody;
 use crate::Orchestrator;
 
 #[derive(Resource)]
@@ -10,20 +21,46 @@ pub struct OrchestratorResource {
     pub orchestrator: Orchestrator
 }
 
-#[derive(Resource, PartialEq, Eq)]
+#[derive(Resource, PartialEq, Eq, Clone, Copy, Debug)]
 pub enum GameState {
     WaitingStart,
     Playing,
     Paused,
-    Override
+    Override,
+    /// Showing a past tick, the game is paused
+    Reviewing,
+    /// Every planet is dead or every explorer has stopped,
+    /// no more ticks are played
+    Finished
 }
 
-#[derive(Resource, Clone)]
+#[derive(Resource, Clone, Serialize, Deserialize)]
 pub struct GalaxySnapshot {
     pub edges: Vec<(u32, u32)>,
     pub planet_num: usize
 }
 
+/// Sprite of every planet, ghost planet and explorer on screen, by id.
+/// Kept up to date by the hooks of [`Planet`](super::components::Planet),
+/// [`GhostPlanet`](super::components::GhostPlanet) and [`Explorer`](super::components::Explorer).
+#[derive(Resource, Default)]
+pub(crate) struct GalaxyRegistry {
+    pub planets: HashMap<u32, Entity>,
+    pub ghosts: HashMap<u32, Entity>,
+    pub explorers: HashMap<u32, Entity>
+}
+
+impl GalaxyRegistry {
+    pub fn planet(&self, planet_id: u32) -> Option<Entity> { self.planets.get(&planet_id).copied() }
+
+    pub fn ghost(&self, planet_id: u32) -> Option<Entity> { self.ghosts.get(&planet_id).copied() }
+
+    /// Sprite an edge to this planet is attached to, alive or not
+    pub fn link_end(&self, planet_id: u32) -> Option<Entity> { self.planet(planet_id).or(self.ghost(planet_id)) }
+
+    pub fn explorer(&self, explorer_id: u32) -> Option<Entity> { self.explorers.get(&explorer_id).copied() }
+}
+
 #[derive(Resource, Debug)]
 pub struct EntityClickRes {
     pub planet: Option<u32>,
@@ -40,10 +77,243 @@ pub struct ExplorerInfoRes {
     pub map: ExplorerInfoMap
 }
 
+/// Latest entries of the event log, oldest first.
+/// Once full, every new entry drops the oldest one.
 #[derive(Resource)]
-pub struct LogTextRes {
-    pub text: VecDeque<String>
+pub struct EventLog {
+    entries: VecDeque<LogEntry>,
+    capacity: usize,
+    /// Entries pushed so far, the dropped ones included
+    pushed: usize
+}
+
+impl EventLog {
+    pub fn new(capacity: usize) -> Self { Self { entries: VecDeque::with_capacity(capacity), capacity, pushed: 0 } }
+
+    pub fn push(&mut self, entry: LogEntry) {
+        if self.entries.len() >= self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+        self.pushed += 1;
+    }
+
+    pub fn entries(&self) -> &VecDeque<LogEntry> { &self.entries }
+
+    /// Number of entries pushed so far, the `i`-th of [`entries`](Self::entries)
+    /// was the `first_number() + i`-th one
+    pub fn pushed(&self) -> usize { self.pushed }
+
+    /// Number of the oldest entry still kept
+    pub fn first_number(&self) -> usize { self.pushed - self.entries.len() }
+
+    pub fn into_entries(self) -> VecDeque<LogEntry> { self.entries }
+}
+
+/// Numbers charted by the statistics dashboard, one sample per tick
+#[derive(Resource, Default)]
+pub struct GameStats {
+    /// Sorted by tick
+    pub samples: Vec<TickStats>,
+    /// How every dead planet died, by planet id
+    pub deaths: BTreeMap<u32, PlanetDeath>,
+    /// Last celestial sent to every planet, what a dying planet was hit by
+    pub last_celestial: HashMap<u32, CelestialBody>
+}
+
+#[derive(Default, Clone)]
+pub struct TickStats {
+    pub tick: u32,
+    /// Planets still alive at the end of the tick
+    pub alive_planets: usize,
+    /// Charged cells of all the alive planets
+    pub charged_cells: usize,
+    /// Sunrays sent during the tick
+    pub sunrays: u32,
+    /// Asteroids sent during the tick
+    pub asteroids: u32,
+    /// Resources generated or combined during the tick, by explorer id
+    pub resources: HashMap<u32, u32>
+}
+
+#[derive(Clone, Copy)]
+pub struct PlanetDeath {
+    pub tick: u32,
+    pub cause: DeathCause
+}
+
+impl GameStats {
+    /// Sample of `tick`, added if it is missing
+    pub fn at(&mut self, tick: u32) -> &mut TickStats {
+        let index = match self.samples.binary_search_by_key(&tick, |s| s.tick) {
+            Ok(index) => index,
+            Err(index) => {
+                self.samples.insert(index, TickStats { tick, ..default() });
+                index
+            }
+        };
+        &mut self.samples[index]
+    }
+}
+
+/// Entries shown by the log panel
+#[derive(Resource, Default)]
+pub struct LogFilter {
+    /// Kinds toggled off
+    pub hidden: HashSet<LogKind>,
+    /// Only entries containing this are shown, ignoring case
+    pub search: String,
+    /// Whether the keys pressed go to the search box
+    pub typing: bool
 }
 
 #[derive(Resource, Deref, DerefMut)]
 pub struct GameTimer(pub Timer);
+
+/// Number of orchestrator steps played so far
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct TickCounter(pub u32);
+
+/// Multiplier of the game speed, applied to the [`GameTimer`],
+/// every running tween and the death, impact and trail animations
+#[derive(Resource, Deref)]
+pub struct SimulationSpeed(pub f32);
+
+impl SimulationSpeed {
+    pub const MIN: f32 = 0.25;
+    pub const MAX: f32 = 16.;
+
+    /// Seconds between two ticks at this speed
+    pub fn tick_seconds(&self, settings: &GuiSettings) -> f32 { settings.tick_seconds / self.0 }
+}
+
+impl Default for SimulationSpeed {
+    fn default() -> Self { Self(1.) }
+}
+
+/// Set to play exactly one tick while the game is paused
+#[derive(Resource, Default)]
+pub struct StepRequest(pub bool);
+
+/// State of the galaxy after the latest ticks, starting from
+/// the one before the first tick, so it is never empty.
+/// A live game only keeps the last `capacity` frames.
+#[derive(Resource)]
+pub(crate) struct GameHistory {
+    pub frames: Vec<RecordedTick>,
+    /// Frame shown in the galaxy view
+    pub current: usize,
+    capacity: usize,
+    /// What the export needs of the frames dropped once full, oldest first
+    pub dropped: Vec<TickSummary>
+}
+
+impl GameHistory {
+    pub fn new(frames: Vec<RecordedTick>, capacity: usize) -> Self {
+        Self { frames, current: 0, capacity, dropped: Vec::new() }
+    }
+
+    pub fn latest(&self) -> usize { self.frames.len() - 1 }
+
+    /// Adds a new frame and shows it, dropping the oldest one if full
+    /// but keeping its summary for the export
+    pub fn push(&mut self, frame: RecordedTick) {
+        if self.frames.len() >= self.capacity {
+            let oldest = self.frames.remove(0);
+            self.dropped.push(TickSummary::new(&oldest));
+            // the first frame always has the links, the others only when they change
+            if let Some(first) = self.frames.first_mut()
+                && first.edges.is_none()
+            {
+                first.edges = oldest.edges;
+            }
+        }
+
+        self.frames.push(frame);
+        self.current = self.latest();
+    }
+}
+
+/// Options chosen when building the
+/// [`GalaxyGuiPlugin`](super::super::GalaxyGuiPlugin)
+#[derive(Resource, Clone)]
+pub struct GuiSettings {
+    /// Seconds between two orchestrator steps
+    pub tick_seconds: f32,
+    /// Size of the primary window, if it should be changed
+    pub window_size: Option<Vec2>,
+    /// State the game is in once the orchestrator is ready
+    pub starting_state: GameState,
+    /// Seed of the [`SessionRng`]
+    pub seed: u64,
+    /// File every tick is recorded to, if any
+    pub record_path: Option<PathBuf>,
+    /// Recording played back instead of a live orchestrator
+    pub replay_path: Option<PathBuf>,
+    /// How planets are placed in the galaxy view
+    pub layout: LayoutKind,
+    /// Whether planets glide to their new positions when the layout changes
+    pub animate_layout: bool,
+    /// Part of a tick an explorer takes to travel between two planets
+    pub travel_fraction: f32,
+    /// Whether dead planets stay on screen, greyed out
+    pub ghost_planets: bool,
+    /// Where sunrays and asteroids come from
+    pub celestial_entry: CelestialEntry,
+    /// Entries kept by the [`EventLog`]
+    pub log_capacity: usize,
+    /// Frames a live game keeps in the [`GameHistory`]
+    pub history_capacity: usize,
+    /// Directory the "Export log" button writes to
+    pub export_dir: PathBuf
+}
+
+/// Source of the random choices made by the GUI, seeded once per session.
+/// The orchestrator has its own, unseeded, randomness.
+#[derive(Resource)]
+pub struct SessionRng {
+    pub seed: u64,
+    pub rng: StdRng
+}
+
+impl SessionRng {
+    pub fn new(seed: u64) -> Self { Self { seed, rng: StdRng::seed_from_u64(seed) } }
+}
+
+/// Center and zoom the galaxy camera is gliding to,
+/// dropped as soon as the player moves the camera
+#[derive(Resource, Default)]
+pub struct CameraTarget(pub Option<(Vec2, f32)>);
+
+/// Last entity clicked in the galaxy, to tell double clicks apart
+#[derive(Resource, Default)]
+pub struct LastClick {
+    pub entity: Option<Entity>,
+    pub time: f32
+}
+
+/// Messages shown by the inspector, `None` shows everyone
+#[derive(Resource, Default)]
+pub struct MessageFilter {
+    pub explorer: Option<u32>,
+    pub planet: Option<u32>
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::event_log::Severity;
+    use super::*;
+
+    #[test]
+    fn event_log_drops_the_oldest_entry_once_full() {
+        let mut log = EventLog::new(2);
+        for tick in 0..3 {
+            log.push(LogEntry::new(tick, Severity::Info, LogKind::Game, format!("tick {tick}")));
+        }
+
+        let ticks: Vec<u32> = log.entries().iter().map(|entry| entry.tick).collect();
+        assert_eq!(ticks, [1, 2]);
+        assert_eq!(log.pushed(), 3);
+        assert_eq!(log.first_number(), 1);
+    }
+}
@@ -0,0 +1,301 @@
+use std::collections::VecDeque;
+
+use bevy::input::ButtonState;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::prelude::*;
+
+use super::ecs::components::{ButtonActions, LogFilterLabel, LogList, LogRow};
+use super::ecs::resources::{EntityClickRes, EventLog, LogFilter};
+use super::ui::filter_button;
+
+/// How bad the thing an entry reports is
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical
+}
+
+impl Severity {
+    fn color(self) -> Color {
+        match self {
+            Severity::Info => Color::srgb(0.97, 0.98, 0.96),
+            Severity::Warning => Color::srgb(1., 0.8, 0.3),
+            Severity::Critical => Color::srgb(1., 0.4, 0.35)
+        }
+    }
+}
+
+/// What an entry is about, the log panel can hide each kind
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum LogKind {
+    Sunray,
+    Asteroid,
+    Death,
+    Move,
+    Resource,
+    /// Notes about the game itself, always shown
+    Game
+}
+
+impl LogKind {
+    /// Kinds with a toggle in the log panel
+    pub(crate) const FILTERABLE: [LogKind; 5] =
+        [LogKind::Sunray, LogKind::Asteroid, LogKind::Death, LogKind::Move, LogKind::Resource];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LogKind::Sunray => "Sunray",
+            LogKind::Asteroid => "Asteroid",
+            LogKind::Death => "Death",
+            LogKind::Move => "Move",
+            LogKind::Resource => "Resource",
+            LogKind::Game => "Game"
+        }
+    }
+}
+
+/// Line of the event log
+#[derive(Clone, Debug)]
+pub struct LogEntry {
+    /// Tick the event happened in
+    pub tick: u32,
+    pub severity: Severity,
+    pub kind: LogKind,
+    /// Planet the entry is about, if any
+    pub planet: Option<u32>,
+    /// Explorer the entry is about, if any
+    pub explorer: Option<u32>,
+    pub text: String
+}
+
+impl LogEntry {
+    pub fn new(tick: u32, severity: Severity, kind: LogKind, text: impl Into<String>) -> Self {
+        Self { tick, severity, kind, planet: None, explorer: None, text: text.into() }
+    }
+
+    pub fn with_planet(mut self, planet_id: u32) -> Self {
+        self.planet = Some(planet_id);
+        self
+    }
+
+    pub fn with_explorer(mut self, explorer_id: u32) -> Self {
+        self.explorer = Some(explorer_id);
+        self
+    }
+
+    fn matches(&self, filter: &LogFilter) -> bool {
+        !filter.hidden.contains(&self.kind)
+            && (filter.search.is_empty() || self.text.to_lowercase().contains(&filter.search.to_lowercase()))
+    }
+}
+
+/// Filter toggles, search box and entry list,
+/// filling the bottom of the game menu
+pub(crate) fn spawn_log_panel(parent: &mut ChildSpawnerCommands) {
+    parent
+        .spawn(Node { flex_direction: FlexDirection::Row, flex_wrap: FlexWrap::Wrap, ..default() })
+        .with_children(|parent| {
+            for kind in LogKind::FILTERABLE {
+                parent.spawn(filter_button(
+                    format!("{}: on", kind.name()),
+                    LogFilterLabel::Kind(kind),
+                    ButtonActions::ToggleLogKind(kind)
+                ));
+            }
+        });
+
+    parent.spawn(filter_button(
+        "Search: click to type",
+        LogFilterLabel::Search,
+        ButtonActions::FocusLogSearch
+    ));
+
+    parent.spawn((
+        BackgroundColor(Color::Srgba(Srgba { red: 0., green: 0., blue: 0., alpha: 0.6 })),
+        Node {
+            flex_direction: FlexDirection::Column,
+            align_self: AlignSelf::Stretch,
+            flex_grow: 1.,
+            overflow: Overflow::scroll_y(),
+            ..default()
+        },
+        LogList
+    ));
+}
+
+pub(crate) fn log_filter_action(
+    action_query: Query<(&Interaction, &ButtonActions), (Changed<Interaction>, With<Button>)>,
+    mut filter: ResMut<LogFilter>
+) {
+    for (&interaction, action) in &action_query {
+        if interaction == Interaction::Pressed {
+            match action {
+                ButtonActions::ToggleLogKind(kind) =>
+                    if !filter.hidden.remove(kind) {
+                        filter.hidden.insert(*kind);
+                    },
+                ButtonActions::FocusLogSearch => {
+                    filter.typing = !filter.typing;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Types in the search box while it has the focus,
+/// Enter or Escape give the focus back
+pub(crate) fn type_log_search(mut keyboard_reader: MessageReader<KeyboardInput>, mut filter: ResMut<LogFilter>) {
+    if !filter.typing {
+        keyboard_reader.clear();
+        return;
+    }
+
+    for input in keyboard_reader.read() {
+        if input.state != ButtonState::Pressed {
+            continue;
+        }
+
+        match &input.logical_key {
+            Key::Character(text) => filter.search.push_str(text),
+            Key::Space => filter.search.push(' '),
+            Key::Backspace => {
+                filter.search.pop();
+            }
+            Key::Enter | Key::Escape => filter.typing = false,
+            _ => {}
+        }
+    }
+}
+
+/// Selects the entity a clicked entry is about,
+/// the explorer if it names both
+pub(crate) fn log_row_action(
+    row_query: Query<(&Interaction, &LogRow), (Changed<Interaction>, With<Button>)>,
+    mut chosen_entity: ResMut<EntityClickRes>
+) {
+    for (&interaction, row) in &row_query {
+        if interaction != Interaction::Pressed {
+            continue;
+        }
+
+        if let Some(explorer_id) = row.explorer {
+            chosen_entity.explorer = Some(explorer_id);
+            chosen_entity.planet = None;
+        } else if let Some(planet_id) = row.planet {
+            chosen_entity.planet = Some(planet_id);
+            chosen_entity.explorer = None;
+        }
+    }
+}
+
+/// Rows of the log list, oldest first, along with the number of their entry
+#[derive(Default)]
+pub(crate) struct LogRows {
+    rows: VecDeque<(usize, Entity)>,
+    /// Entries of the log already gone through
+    seen: usize
+}
+
+/// Lists the entries matching the filter, newest first.
+/// Rows are added and dropped along with the entries,
+/// the whole list is only rebuilt when the filter changes
+pub(crate) fn update_log_list(
+    mut commands: Commands,
+    log: Res<EventLog>,
+    filter: Res<LogFilter>,
+    list: Single<Entity, With<LogList>>,
+    mut labels: Query<(&mut Text, &LogFilterLabel)>,
+    mut shown: Local<LogRows>
+) {
+    if !log.is_changed() && !filter.is_changed() {
+        return;
+    }
+
+    if filter.is_changed() {
+        for (mut text, label) in &mut labels {
+            **text = match label {
+                LogFilterLabel::Kind(kind) => {
+                    let state = if filter.hidden.contains(kind) { "off" } else { "on" };
+                    format!("{}: {}", kind.name(), state)
+                }
+                LogFilterLabel::Search => match (filter.typing, filter.search.is_empty()) {
+                    (true, _) => format!("Search: {}_", filter.search),
+                    (false, true) => "Search: click to type".to_string(),
+                    (false, false) => format!("Search: {}", filter.search)
+                }
+            };
+        }
+    }
+
+    // a restarted game comes with a new log, shorter than the old one
+    if filter.is_changed() || log.is_added() || log.pushed() < shown.seen {
+        commands.entity(*list).despawn_children();
+        shown.rows.clear();
+        shown.seen = log.first_number();
+    }
+
+    // rows of the entries the log dropped
+    let first = log.first_number();
+    while let Some(&(number, row)) = shown.rows.front()
+        && number < first
+    {
+        commands.entity(row).despawn();
+        shown.rows.pop_front();
+    }
+
+    let mut new_rows = Vec::new();
+    for (i, entry) in log.entries().iter().enumerate().skip(shown.seen.saturating_sub(first)) {
+        if entry.matches(&filter) {
+            let row = spawn_log_row(&mut commands, entry);
+            shown.rows.push_back((first + i, row));
+            new_rows.push(row);
+        }
+    }
+    shown.seen = log.pushed();
+
+    // the newest entries go on top
+    new_rows.reverse();
+    commands.entity(*list).insert_children(0, &new_rows);
+}
+
+fn spawn_log_row(commands: &mut Commands, entry: &LogEntry) -> Entity {
+    let mut row = commands.spawn((
+        Text::new(format!("[tick {}] {}", entry.tick, entry.text)),
+        TextFont { font_size: 12., ..default() },
+        TextColor(entry.severity.color())
+    ));
+
+    // only entries about a planet or explorer can be clicked
+    if entry.planet.is_some() || entry.explorer.is_some() {
+        row.insert((Button, LogRow { planet: entry.planet, explorer: entry.explorer }));
+    }
+
+    row.id()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(kind: LogKind, text: &str) -> LogEntry { LogEntry::new(0, Severity::Info, kind, text) }
+
+    #[test]
+    fn entries_of_hidden_kinds_do_not_match() {
+        let mut filter = LogFilter::default();
+        assert!(entry(LogKind::Sunray, "sunray sent to planet 1").matches(&filter));
+
+        filter.hidden.insert(LogKind::Sunray);
+        assert!(!entry(LogKind::Sunray, "sunray sent to planet 1").matches(&filter));
+        assert!(entry(LogKind::Asteroid, "asteroid sent to planet 1").matches(&filter));
+    }
+
+    #[test]
+    fn search_ignores_case() {
+        let filter = LogFilter { search: "PLANET 2".to_string(), ..default() };
+
+        assert!(entry(LogKind::Death, "Planet 2 died").matches(&filter));
+        assert!(!entry(LogKind::Death, "Planet 3 died").matches(&filter));
+    }
+}
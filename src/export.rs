@@ -0,0 +1,188 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use bevy::prelude::*;
+use serde::Serialize;
+
+use super::ecs::components::ButtonActions;
+use super::ecs::resources::{EventLog, GameHistory, GuiSettings};
+use super::event_log::{LogEntry, LogKind, Severity};
+use super::galaxy::explorer_ids;
+use super::replay::{RecordedEvent, RecordedTick};
+use crate::app::AppConfig;
+
+/// First line of the JSON Lines export, and comment on top of the CSV one
+#[derive(Serialize)]
+struct ExportMetadata {
+    seed: u64,
+    number_of_planets: u32,
+    explorer_ids: Vec<u32>,
+    initial_planet_id: u32,
+    game_tick_seconds: f32,
+    ticks: u32
+}
+
+#[derive(Serialize)]
+struct PlanetSummary {
+    id: u32,
+    status: String,
+    charged_cells: usize,
+    energy_cells: usize,
+    rocket: bool
+}
+
+#[derive(Serialize)]
+struct ExplorerSummary {
+    id: u32,
+    status: String,
+    planet: u32,
+    bag: String
+}
+
+/// Every other line of the JSON Lines export, also kept by the
+/// [`GameHistory`] for the frames it drops, so the export still has them
+#[derive(Serialize)]
+pub(crate) struct TickSummary {
+    tick: u32,
+    events: Vec<RecordedEvent>,
+    planets: Vec<PlanetSummary>,
+    explorers: Vec<ExplorerSummary>
+}
+
+impl TickSummary {
+    pub fn new(frame: &RecordedTick) -> Self {
+        let mut planets: Vec<PlanetSummary> = frame
+            .planets
+            .iter()
+            .map(|(&id, info)| PlanetSummary {
+                id,
+                status: format!("{:?}", info.status),
+                charged_cells: info.charged_cells_count,
+                energy_cells: info.energy_cells.len(),
+                rocket: info.rocket
+            })
+            .collect();
+        planets.sort_unstable_by_key(|p| p.id);
+
+        let explorers = explorer_ids()
+            .filter_map(|id| {
+                let info = frame.explorers.get(&id)?;
+                Some(ExplorerSummary {
+                    id,
+                    status: format!("{:?}", info.status),
+                    planet: info.current_planet_id,
+                    bag: format!("{:?}", info.bag)
+                })
+            })
+            .collect();
+
+        Self { tick: frame.tick, events: frame.events.clone(), planets, explorers }
+    }
+}
+
+/// Writes every tick of the game, the ones the history has dropped included
+/// and not only the ones up to the frame shown,
+/// to a JSON Lines and a CSV file named after the seed and the last tick
+pub(crate) fn export_action(
+    action_query: Query<(&Interaction, &ButtonActions), (Changed<Interaction>, With<Button>)>,
+    history: Res<GameHistory>,
+    settings: Res<GuiSettings>,
+    mut log: ResMut<EventLog>
+) {
+    for (&interaction, action) in &action_query {
+        if interaction == Interaction::Pressed
+            && let ButtonActions::ExportLog = action
+        {
+            let cfg = AppConfig::get();
+            let frames = &history.frames;
+            let last_tick = frames.last().map_or(0, |f| f.tick);
+
+            let metadata = ExportMetadata {
+                seed: settings.seed,
+                number_of_planets: cfg.number_of_planets,
+                explorer_ids: explorer_ids().collect(),
+                initial_planet_id: cfg.initial_planet_id,
+                game_tick_seconds: cfg.game_tick_seconds,
+                ticks: last_tick
+            };
+            let kept: Vec<TickSummary> = frames.iter().map(TickSummary::new).collect();
+            let summaries: Vec<&TickSummary> = history.dropped.iter().chain(&kept).collect();
+
+            let base = settings.export_dir.join(format!("galaxy-{}-tick-{}", settings.seed, last_tick));
+            let (jsonl, csv) = (base.with_extension("jsonl"), base.with_extension("csv"));
+
+            match write_jsonl(&jsonl, &metadata, &summaries).and_then(|_| write_csv(&csv, &metadata, &summaries)) {
+                Ok(()) => {
+                    info!("exported the game to {} and {}", jsonl.display(), csv.display());
+                    let text = format!("log exported to {}", base.display());
+                    log.push(LogEntry::new(last_tick, Severity::Info, LogKind::Game, text));
+                }
+                Err(e) => {
+                    log::error!("Failed to export the game to {}: {e}", base.display());
+                    let text = format!("export failed: {e}");
+                    log.push(LogEntry::new(last_tick, Severity::Warning, LogKind::Game, text));
+                }
+            }
+        }
+    }
+}
+
+fn write_jsonl(path: &Path, metadata: &ExportMetadata, summaries: &[&TickSummary]) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    serde_json::to_writer(&mut writer, metadata)?;
+    writer.write_all(b"\n")?;
+
+    for summary in summaries {
+        serde_json::to_writer(&mut writer, summary)?;
+        writer.write_all(b"\n")?;
+    }
+
+    writer.flush()
+}
+
+/// One row per event, planet and explorer of every tick,
+/// the `kind` column tells them apart
+fn write_csv(path: &Path, metadata: &ExportMetadata, summaries: &[&TickSummary]) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    // most CSV readers can skip comment lines
+    writeln!(writer, "# {}", serde_json::to_string(metadata)?)?;
+    writeln!(writer, "tick,kind,id,status,planet,charged_cells,energy_cells,rocket,detail")?;
+
+    for summary in summaries {
+        let tick = summary.tick;
+
+        for event in &summary.events {
+            let (id, planet) = match *event {
+                RecordedEvent::PlanetDestroyed { planet_id }
+                | RecordedEvent::SunrayReceived { planet_id }
+                | RecordedEvent::SunraySent { planet_id }
+                | RecordedEvent::AsteroidSent { planet_id } => (planet_id, planet_id.to_string()),
+                RecordedEvent::ExplorerMoved { explorer_id, destination } => (explorer_id, destination.to_string()),
+                RecordedEvent::BasicResourceGenerated { explorer_id, .. }
+                | RecordedEvent::ComplexResourceGenerated { explorer_id, .. } => (explorer_id, String::new())
+            };
+            let detail = csv_field(&format!("{:?}", event));
+            writeln!(writer, "{tick},event,{id},,{planet},,,,{detail}")?;
+        }
+
+        for p in &summary.planets {
+            writeln!(
+                writer,
+                "{tick},planet,{},{},{},{},{},{},",
+                p.id, p.status, p.id, p.charged_cells, p.energy_cells, p.rocket
+            )?;
+        }
+
+        for e in &summary.explorers {
+            writeln!(writer, "{tick},explorer,{},{},{},,,,{}", e.id, e.status, e.planet, csv_field(&e.bag))?;
+        }
+    }
+
+    writer.flush()
+}
+
+/// Quotes a field, doubling the quotes inside it
+fn csv_field(text: &str) -> String { format!("\"{}\"", text.replace('"', "\"\"")) }
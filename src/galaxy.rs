@@ -1,29 +1,43 @@
-use std::f32::consts::TAU;
+use std::collections::{HashMap, HashSet};
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI, TAU};
 use std::time::Duration;
 
+use bevy::ecs::query::QueryFilter;
 use bevy::prelude::*;
 use bevy_tweening::lens::TransformPositionLens;
-use bevy_tweening::{CycleCompletedEvent, Tween, TweenAnim};
-
-use super::ecs::components::{Edge, Explorer, Planet, UiExplorerText, UiPlanetText};
-use super::ecs::events::{Celestial, CelestialBody, MoveExplorerEvent, PlanetDespawn};
-use super::ecs::resources::{EntityClickRes, ExplorerInfoRes, GalaxySnapshot, PlanetInfoRes};
-use super::types::Status;
-use super::utils::assets::{CelestialAssets, ExplorerAssets, PlanetAssets};
-use super::utils::constants::{CELESTIAL_RAD, EXP_MATTIA_OFFSET, EXP_SPRITE_NUM, EXP_TOMMY_OFFSET, EXPLORER_SIZE,
-                              GALAXY_RADIUS, PLANET_RAD, PLANET_SPRITE_NUM};
+use bevy_tweening::{CycleCompletedEvent, Sequence, Tween, TweenAnim};
+use common_game::components::resource::{BasicResourceType, ComplexResourceType};
+
+use super::ecs::components::{DeathKind, Dying, Edge, EnergySegment, Explorer, GalaxyCamera, GhostPlanet, Impact,
+                             Planet, ResourcePopup, RocketBadge, TrailDot, UiExplorerText, UiPlanetText};
+use super::ecs::events::{BasicResEvent, Celestial, CelestialBody, ComplexResEvent, MoveExplorerEvent, PlanetDespawn};
+use super::ecs::resources::{EntityClickRes, ExplorerInfoRes, GalaxyRegistry, GalaxySnapshot, GuiSettings,
+                            PlanetInfoRes, SimulationSpeed};
+use super::layout::GalaxyLayout;
+use super::status::spawn_status_icon;
+use super::types::{ExplorerInfo, PlanetInfo, Status};
+use super::utils::assets::{ExplorerAssets, PlanetAssets};
+use super::utils::constants::{DASH_LENGTH, DEATH_SECONDS, EMPTY_CELL_COLOR, ENERGY_COLOR, ENERGY_RING_RADIUS,
+                              ENERGY_SEGMENT_THICKNESS, EXP_SPRITE_NUM, EXPLORER_RING_RADIUS, EXPLORER_SIZE,
+                              GHOST_COLOR, PLANET_RAD, PLANET_SPRITE_NUM, RESOURCE_ICON_SIZE, RESOURCE_POPUP_RISE,
+                              ROCKET_BADGE_SIZE, ROCKET_COLOR, TRAIL_DOT_SIZE, TRAIL_SECONDS, TRAIL_SPACING};
 use crate::app::AppConfig;
 use crate::orchestrator::PlanetType;
 
+/// Sprites edges can be attached to
+type PlanetOrGhost = Or<(With<Planet>, With<GhostPlanet>)>;
+
 pub fn setup(
-    galaxy: Res<GalaxySnapshot>,
+    settings: Res<GuiSettings>,
+    layout: Res<GalaxyLayout>,
     planets: Res<PlanetInfoRes>,
+    explorers: Res<ExplorerInfoRes>,
     mut commands: Commands,
     asset_loader: Res<AssetServer>,
     planet_assets: Res<PlanetAssets>,
     explorer_assets: Res<ExplorerAssets>
 ) {
-    commands.spawn(Camera2d);
+    commands.spawn((Camera2d, GalaxyCamera));
 
     //create and load background image through sprites
     let background: Handle<Image> = asset_loader.load("sky.png");
@@ -34,18 +48,48 @@ pub fn setup(
         ..Default::default()
     });
 
-    let planet_num = galaxy.planet_num;
+    let ghosts = settings.ghost_planets;
+    spawn_galaxy(&mut commands, &layout, &planets, &explorers, &planet_assets, &explorer_assets, ghosts);
+}
+
+/// Spawns the galaxy of a game that has just been set up
+pub(crate) fn respawn_galaxy(
+    mut commands: Commands,
+    settings: Res<GuiSettings>,
+    layout: Res<GalaxyLayout>,
+    planets: Res<PlanetInfoRes>,
+    explorers: Res<ExplorerInfoRes>,
+    planet_assets: Res<PlanetAssets>,
+    explorer_assets: Res<ExplorerAssets>
+) {
+    let ghosts = settings.ghost_planets;
+    spawn_galaxy(&mut commands, &layout, &planets, &explorers, &planet_assets, &explorer_assets, ghosts);
+}
+
+/// Spawns the sprites of every planet still alive and of the explorers
+/// visiting them, as described by the planet and explorer info.
+/// Dead planets are left as ghosts if `ghosts` is set.
+/// Edges are left to [`draw_topology`].
+pub(crate) fn spawn_galaxy(
+    commands: &mut Commands,
+    layout: &GalaxyLayout,
+    planets: &PlanetInfoRes,
+    explorers: &ExplorerInfoRes,
+    planet_assets: &PlanetAssets,
+    explorer_assets: &ExplorerAssets,
+    ghosts: bool
+) {
+    for (&i, info) in planets.map.iter() {
+        let dead = info.status == Status::Dead;
 
-    for (&i, _info) in planets.map.iter() {
-        // spawn all the planets in a circle, with even spacing
-        // Tau = 2 * pi, so all the planets go around the circle
-        let angle = TAU * (i as f32) / (planet_num as f32);
+        // dead planets have already been despawned
+        if dead && !ghosts {
+            continue;
+        }
 
-        // extract x and y position via trigonometry
-        let x = GALAXY_RADIUS * angle.cos();
-        let y = GALAXY_RADIUS * angle.sin();
+        let position = layout.position(i);
 
-        let image_index = match planets.map.get_info(i).unwrap().name {
+        let image_index = match info.name {
             PlanetType::PanicOutOfOxygen => 0,
             PlanetType::RustEze => 1,
             PlanetType::HoustonWeHaveABorrow => 2,
@@ -60,6 +104,20 @@ pub fn setup(
         // in case the index is out of bounds
         let planet_image_handle = planet_assets.handles[(image_index) % PLANET_SPRITE_NUM].clone();
 
+        if dead {
+            commands.spawn((
+                GhostPlanet { id: i },
+                Sprite {
+                    image: planet_image_handle,
+                    color: GHOST_COLOR,
+                    custom_size: Some(Vec2::splat(PLANET_RAD * 2.)),
+                    ..Default::default()
+                },
+                Transform::from_xyz(position.x, position.y, 2.0)
+            ));
+            continue;
+        }
+
         commands
             .spawn((
                 Planet { id: i },
@@ -68,180 +126,617 @@ pub fn setup(
                     custom_size: Some(Vec2::splat(PLANET_RAD * 2.)),
                     ..Default::default()
                 },
-                Transform::from_xyz(x, y, 2.0),
+                Transform::from_xyz(position.x, position.y, 2.0),
+                Pickable::default()
+            ))
+            .with_children(|parent| {
+                spawn_energy_ring(parent, info);
+                spawn_status_icon(parent, Vec2::new(-PLANET_RAD * 0.75, PLANET_RAD * 0.75));
+            })
+            .observe(choose_on_click);
+    }
+
+    // explorers die along with the planet they are visiting
+    let visible: Vec<(usize, u32, u32)> = explorer_ids()
+        .enumerate()
+        .filter_map(|(j, id)| {
+            let info = explorers.map.get(&id)?;
+            (planets.map.get_status(&info.current_planet_id) != Status::Dead).then_some((j, id, info.current_planet_id))
+        })
+        .collect();
+
+    for &(j, id, current_planet) in &visible {
+        // place in the ring of the host planet, by id
+        let guests = visible.iter().filter(|(_, _, p)| *p == current_planet);
+        let index = guests.clone().filter(|(_, other, _)| *other < id).count();
+        let offset = ring_offset(index, guests.count());
+
+        let position = layout.position(current_planet) + offset;
+
+        // sprites are shared when there are more explorers than images,
+        // the tint tells them apart
+        let explorer_image_handle = explorer_assets.handles[j % EXP_SPRITE_NUM].clone();
+        let tint = explorer_tint(j);
+        commands
+            .spawn((
+                Explorer { id, current_planet, position_offset: (offset.x, offset.y), tint },
+                Sprite {
+                    image: explorer_image_handle,
+                    color: tint,
+                    custom_size: Some(Vec2::splat(EXPLORER_SIZE)),
+                    ..Default::default()
+                },
+                Transform::from_xyz(position.x, position.y, 3.0),
                 Pickable::default()
             ))
+            .with_children(|parent| spawn_status_icon(parent, Vec2::new(0., EXPLORER_SIZE * 0.6)))
             .observe(choose_on_click);
+    }
+}
 
-        let cfg = AppConfig::get();
-        if i == cfg.initial_planet_id {
-            for j in 0..cfg.explorers.len() {
-                let explorer_image_handle = explorer_assets.handles[j % EXP_SPRITE_NUM].clone();
-                let (offset_x, offset_y): (f32, f32) = if j == 0 { EXP_TOMMY_OFFSET } else { EXP_MATTIA_OFFSET };
-                commands
-                    .spawn((
-                        Explorer {
-                            id: j as u32 + cfg.number_of_planets + 1,
-                            current_planet: i,
-                            position_offset: (offset_x, offset_y)
-                        },
-                        Sprite {
-                            image: explorer_image_handle,
-                            custom_size: Some(Vec2::splat(EXPLORER_SIZE)),
-                            ..Default::default()
-                        },
-                        Transform::from_xyz(x + offset_x, y - offset_y, 3.0),
-                        Pickable::default()
-                    ))
-                    .observe(choose_on_click);
+/// Ids of the explorers of the configuration, which come right after the planet ones
+pub(crate) fn explorer_ids() -> impl Iterator<Item = u32> {
+    let cfg = AppConfig::get();
+    let first = cfg.number_of_planets + 1;
+    (0..cfg.explorers.len() as u32).map(move |j| first + j)
+}
+
+/// Offset from its host planet of the `index`-th of `count` explorers
+/// visiting it, all evenly spread on a ring
+pub(crate) fn ring_offset(index: usize, count: usize) -> Vec2 {
+    let angle = FRAC_PI_4 + TAU * index as f32 / count.max(1) as f32;
+    Vec2::from_angle(angle) * EXPLORER_RING_RADIUS
+}
+
+/// Colour of the `index`-th explorer, hues are spread by the
+/// golden angle so any number of explorers stays distinct
+pub(crate) fn explorer_tint(index: usize) -> Color { Color::hsl((index as f32 * 137.508) % 360., 0.8, 0.75) }
+
+/// Adds and removes edge sprites so they match the snapshot,
+/// the ones already on screen are only restyled when needed.
+/// Links to planets that no longer exist are not drawn,
+/// the ones to ghost planets are dashed.
+pub fn draw_topology(
+    mut commands: Commands,
+    snapshot: Res<GalaxySnapshot>,
+    registry: Res<GalaxyRegistry>,
+    planets: Query<&Transform, PlanetOrGhost>,
+    edges: Query<(Entity, &Edge)>
+) {
+    if !snapshot.is_changed() && !registry.is_changed() {
+        return;
+    }
+
+    // each link counted once, whatever the order of its planets,
+    // along with whether it should be dashed
+    let wanted: HashMap<(u32, u32), bool> = snapshot
+        .edges
+        .iter()
+        .map(|&(a, b)| (a.min(b), a.max(b)))
+        .filter(|&(a, b)| registry.link_end(a).is_some() && registry.link_end(b).is_some())
+        .map(|(a, b)| ((a, b), registry.ghost(a).is_some() || registry.ghost(b).is_some()))
+        .collect();
+
+    let mut drawn = HashSet::new();
+    for (entity, edge) in &edges {
+        let (a, b) = edge.connects;
+        let link = (a.min(b), a.max(b));
+        // gets rid of the links that are gone, and of duplicates
+        let Some(&dashed) = wanted.get(&link).filter(|_| drawn.insert(link)) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+
+        // the links whose planet became a ghost are restyled in place
+        if dashed == edge.dashed {
+            continue;
+        }
+        let (Some(t1), Some(t2)) =
+            (link_end_transform(&registry, &planets, a), link_end_transform(&registry, &planets, b))
+        else {
+            continue;
+        };
+        style_edge(&mut commands.entity(entity), edge.connects, t1.translation, t2.translation, dashed);
+    }
+
+    for (&(a, b), &dashed) in &wanted {
+        if drawn.contains(&(a, b)) {
+            continue;
+        }
+
+        let (Some(t1), Some(t2)) =
+            (link_end_transform(&registry, &planets, a), link_end_transform(&registry, &planets, b))
+        else {
+            continue;
+        };
+
+        style_edge(&mut commands.spawn(Visibility::default()), (a, b), t1.translation, t2.translation, dashed);
+    }
+}
+
+/// Draws an edge as a solid line or as dashes,
+/// replacing whatever it was drawn with before
+fn style_edge(edge: &mut EntityCommands, connects: (u32, u32), start: Vec3, end: Vec3, dashed: bool) {
+    let (transform, length) = edge_transform(start, end);
+    edge.despawn_children().insert(Edge { connects, dashed });
+
+    if !dashed {
+        edge.insert((Sprite { color: Color::WHITE, custom_size: Some(Vec2::new(length, 1.)), ..default() }, transform));
+        return;
+    }
+
+    // dashes are laid out on a segment of length 1,
+    // stretched by the scale of the edge
+    let dashes = (length / (2. * DASH_LENGTH)).max(1.) as usize;
+    edge.remove::<Sprite>().insert(transform.with_scale(Vec3::new(length, 1., 1.))).with_children(|parent| {
+        for i in 0..dashes {
+            let x = -0.5 + (i as f32 + 0.25) / dashes as f32;
+            parent.spawn((
+                Sprite { color: GHOST_COLOR, custom_size: Some(Vec2::new(0.5 / dashes as f32, 1.)), ..default() },
+                Transform::from_xyz(x, 0., 0.)
+            ));
+        }
+    });
+}
+
+/// Transform and length of the edge sprite between two planets
+fn edge_transform(start: Vec3, end: Vec3) -> (Transform, f32) {
+    let length = start.distance(end);
+
+    // diff is the same segment as start and end,
+    // but transposed wrt the origin of the
+    // coordinate system
+    let segment = start - end;
+
+    // finds the rotation of the segment wrt the origin
+    // using the arctangent function
+    let segment_rotation = segment.y.atan2(segment.x);
+    let midpoint = (start + end) / 2.;
+
+    //creates the transform to manipulate the line position
+    let transform =
+        Transform::from_xyz(midpoint.x, midpoint.y, 1.).with_rotation(Quat::from_rotation_z(segment_rotation));
+
+    (transform, length)
+}
+
+/// Stretches the edges of the planets that have moved
+pub(crate) fn follow_planets(
+    moved: Query<(Option<&Planet>, Option<&GhostPlanet>), (Changed<Transform>, PlanetOrGhost, Without<Edge>)>,
+    registry: Res<GalaxyRegistry>,
+    planets: Query<&Transform, (PlanetOrGhost, Without<Edge>)>,
+    mut edges: Query<(&Edge, &mut Transform, Option<&mut Sprite>)>
+) {
+    let moved: HashSet<u32> = moved.iter().filter_map(|(p, g)| p.map(|p| p.id).or(g.map(|g| g.id))).collect();
+    if moved.is_empty() {
+        return;
+    }
+
+    for (edge, mut transform, sprite) in &mut edges {
+        let (a, b) = edge.connects;
+        if !moved.contains(&a) && !moved.contains(&b) {
+            continue;
+        }
+
+        let (Some(t1), Some(t2)) =
+            (link_end_transform(&registry, &planets, a), link_end_transform(&registry, &planets, b))
+        else {
+            continue;
+        };
+
+        let (new_transform, length) = edge_transform(t1.translation, t2.translation);
+
+        // dashed edges have no sprite of their own, see style_edge
+        match sprite {
+            Some(mut sprite) => {
+                *transform = new_transform;
+                sprite.custom_size = Some(Vec2::new(length, 1.));
             }
+            None => *transform = new_transform.with_scale(Vec3::new(length, 1., 1.))
         }
     }
 }
 
-pub fn draw_topology(mut commands: Commands, snapshot: Res<GalaxySnapshot>, planets: Query<(&Planet, &Transform)>) {
-    if snapshot.is_changed() {
-        let gtop = &snapshot.edges; //TODO do something BETTER than this
+/// Transform of a planet on screen
+pub(crate) fn planet_transform<F: QueryFilter>(
+    registry: &GalaxyRegistry,
+    planets: &Query<&Transform, F>,
+    planet_id: u32
+) -> Option<Transform> {
+    registry.planet(planet_id).and_then(|entity| planets.get(entity).ok()).copied()
+}
+
+/// Transform of a planet or ghost on screen
+fn link_end_transform<F: QueryFilter>(
+    registry: &GalaxyRegistry,
+    planets: &Query<&Transform, F>,
+    planet_id: u32
+) -> Option<Transform> {
+    registry.link_end(planet_id).and_then(|entity| planets.get(entity).ok()).copied()
+}
+
+/// One segment per energy cell around the planet, clockwise from the top,
+/// plus the rocket badge, hidden until the planet builds one
+fn spawn_energy_ring(parent: &mut ChildSpawnerCommands, info: &PlanetInfo) {
+    let cells = info.energy_cells.len();
+    let length = (TAU * ENERGY_RING_RADIUS / cells.max(1) as f32 * 0.7).min(PLANET_RAD);
+
+    for index in 0..cells {
+        let angle = FRAC_PI_2 - TAU * (index as f32 + 0.5) / cells as f32;
+        let position = Vec2::from_angle(angle) * ENERGY_RING_RADIUS;
+
+        parent.spawn((
+            EnergySegment { index },
+            Sprite {
+                color: cell_color(index, info),
+                custom_size: Some(Vec2::new(length, ENERGY_SEGMENT_THICKNESS)),
+                ..default()
+            },
+            // lengthwise along the ring
+            Transform::from_xyz(position.x, position.y, 0.1).with_rotation(Quat::from_rotation_z(angle + FRAC_PI_2)),
+            Pickable::IGNORE
+        ));
+    }
+
+    parent
+        .spawn((
+            RocketBadge,
+            Sprite { color: ROCKET_COLOR, custom_size: Some(Vec2::splat(ROCKET_BADGE_SIZE)), ..default() },
+            Transform::from_xyz(PLANET_RAD * 0.75, PLANET_RAD * 0.75, 0.2),
+            rocket_visibility(info),
+            Pickable::IGNORE
+        ))
+        .with_child((
+            Text2d::new("R"),
+            TextFont { font_size: ROCKET_BADGE_SIZE * 0.8, ..default() },
+            TextColor(Color::BLACK),
+            Transform::from_xyz(0., 0., 0.1)
+        ));
+}
 
-        for (a, b) in gtop.iter() {
-            let (_, t1) = planets.iter().find(|(p, _)| p.id as u32 == *a).unwrap();
-            let (_, t2) = planets.iter().find(|(p, _)| p.id as u32 == *b).unwrap();
+fn cell_color(index: usize, info: &PlanetInfo) -> Color {
+    if index < info.charged_cells_count { ENERGY_COLOR } else { EMPTY_CELL_COLOR }
+}
 
-            let start = &t1.translation;
-            let end = &t2.translation;
-            let length = start.distance(*end);
+fn rocket_visibility(info: &PlanetInfo) -> Visibility {
+    if info.rocket { Visibility::Inherited } else { Visibility::Hidden }
+}
 
-            // diff is the same segment as start and end,
-            // but transposed wrt the origin of the
-            // coordinate system
-            let segment = start - end;
+/// Fills the energy rings and shows the rocket badges
+/// according to the latest planet info
+pub(crate) fn update_energy_rings(
+    planet_info: Res<PlanetInfoRes>,
+    planets: Query<&Planet>,
+    mut segments: Query<(&EnergySegment, &ChildOf, &mut Sprite)>,
+    mut badges: Query<(&ChildOf, &mut Visibility), With<RocketBadge>>
+) {
+    if !planet_info.is_changed() {
+        return;
+    }
 
-            // finds the rotation of the segment wrt the origin
-            // using the arctangent function
-            let segment_rotation = segment.y.atan2(segment.x);
-            let midpoint = (start + end) / 2.;
+    let info_of = |child_of: &ChildOf| {
+        let planet = planets.get(child_of.parent()).ok()?;
+        planet_info.map.get_info(planet.id)
+    };
 
-            //creates the transform to manipulate the line position
-            let transform =
-                Transform::from_xyz(midpoint.x, midpoint.y, 1.).with_rotation(Quat::from_rotation_z(segment_rotation));
+    for (segment, child_of, mut sprite) in &mut segments {
+        if let Some(info) = info_of(child_of) {
+            sprite.color = cell_color(segment.index, info);
+        }
+    }
 
-            commands.spawn((
-                Sprite { color: Color::WHITE, custom_size: Some(Vec2::new(length, 1.)), ..default() },
-                transform,
-                Edge { connects: (*a, *b) }
-            ));
+    for (child_of, mut visibility) in &mut badges {
+        if let Some(info) = info_of(child_of) {
+            visibility.set_if_neq(rocket_visibility(info));
         }
     }
 }
 
+/// Blows up a planet and whoever is visiting it.
+///
+/// Their components are removed right away, so they leave the registry
+/// and their links are dropped (or dashed, for ghosts) by [`draw_topology`];
+/// the sprites go away once [`animate_deaths`] is done with them.
 pub fn destroy_link(
     event: On<PlanetDespawn>,
     mut commands: Commands,
-    edge_query: Query<(&Edge, Entity)>,
-    planet_query: Query<(&Planet, Entity)>,
-    explorer_query: Query<(&Explorer, Entity)>
+    settings: Res<GuiSettings>,
+    registry: Res<GalaxyRegistry>,
+    explorer_query: Query<&Explorer>
 ) {
-    //despawn all its links
-    for (e, s) in edge_query {
-        if e.connects.0 == event.planet_id || e.connects.1 == event.planet_id {
-            commands.entity(s).despawn();
+    //if there is an explorer visiting, it dies too
+    for &ent in registry.explorers.values() {
+        if explorer_query.get(ent).is_ok_and(|exp| exp.current_planet == event.planet_id) {
+            commands.entity(ent).remove::<(Explorer, Pickable)>().insert(Dying::new(DeathKind::Explorer));
         }
     }
 
-    //if there is an explorer visiting, despawn
-    for (exp, ent) in explorer_query {
-        if exp.current_planet == event.planet_id {
-            commands.entity(ent).despawn();
-        }
-    }
+    //then the planet itself
+    if let Some(e) = registry.planet(event.planet_id) {
+        let mut planet = commands.entity(e);
+        // the energy ring goes down with it
+        planet.remove::<(Planet, Pickable)>().despawn_children();
 
-    //despawn the planet itself
-    for (p, e) in planet_query {
-        if p.id == event.planet_id {
-            commands.entity(e).despawn();
+        if settings.ghost_planets {
+            planet.insert((GhostPlanet { id: event.planet_id }, Dying::new(DeathKind::Ghost)));
+        } else {
+            planet.insert(Dying::new(DeathKind::Planet));
         }
     }
 }
 
-pub fn move_celestial(
-    event: On<Celestial>,
+pub(crate) fn animate_deaths(
     mut commands: Commands,
-    sprites: Res<CelestialAssets>,
-    planet_query: Query<(&Planet, &Transform)>
+    time: Res<Time>,
+    speed: Res<SimulationSpeed>,
+    mut dying: Query<(Entity, &mut Dying, &mut Transform, &mut Sprite)>
 ) {
-    info!("MOVE_CELESTIAL: EVENT FROM ID {} ", event.planet_id);
-
-    for (p, t) in planet_query {
-        print!("{}, ", p.id);
-        if p.id == event.planet_id {
-            let sunray_sprite = match event.kind {
-                CelestialBody::Sunray => {
-                    info!("spawning sunray sprite");
-                    sprites.handles.0.clone()
-                }
-                CelestialBody::Asteroid => {
-                    info!("spawning asteroid sprite");
-                    sprites.handles.1.clone()
-                }
-            };
-
-            let tween = Tween::new(
-                EaseFunction::QuadraticInOut,
-                Duration::from_secs_f32(AppConfig::get().game_tick_seconds / 2.),
-                TransformPositionLens {
-                    start: Vec3::new(0., 0., 2.0),
-                    end: Vec3::new(t.translation.x, t.translation.y, 2.0)
-                }
-            )
-            .with_cycle_completed_event(true);
+    for (entity, mut dying, mut transform, mut sprite) in &mut dying {
+        dying.age += time.delta_secs() * **speed;
+        let t = (dying.age / DEATH_SECONDS).min(1.);
+
+        match dying.kind {
+            DeathKind::Planet => {
+                // swells into an orange flash and fades away
+                transform.scale = Vec3::splat(1. + t);
+                sprite.color = Color::srgba(1., 0.5, 0.2, 1. - t);
+            }
+            DeathKind::Ghost => {
+                // flares up, then settles down as a grey shadow
+                transform.scale = Vec3::splat(1. + 0.5 * (t * PI).sin());
+                sprite.color = Color::srgba(1. - 0.5 * t, 0.5, 0.2 + 0.3 * t, 1. - (1. - GHOST_COLOR.alpha()) * t);
+            }
+            DeathKind::Explorer => {
+                // spins and shrinks into nothing
+                transform.scale = Vec3::splat(1. - t);
+                transform.rotation = Quat::from_rotation_z(TAU * t);
+                sprite.color = Color::srgba(1., 0.3, 0.3, 1. - t);
+            }
+        }
 
-            commands.spawn((
-                Celestial { kind: event.kind, planet_id: event.planet_id },
-                Sprite { image: sunray_sprite, custom_size: Some(Vec2::splat(CELESTIAL_RAD * 2.)), ..default() },
-                Transform::from_xyz(0., 0., 2.0),
-                TweenAnim::new(tween)
-            ));
+        if t >= 1. {
+            if dying.kind == DeathKind::Ghost {
+                sprite.color = GHOST_COLOR;
+                commands.entity(entity).remove::<Dying>();
+            } else {
+                commands.entity(entity).despawn();
+            }
         }
     }
 }
 
 pub fn move_explorer(
     event: On<MoveExplorerEvent>,
-    mut param_set: ParamSet<(Query<(&mut Explorer, &mut Transform)>, Query<(&Planet, &Transform), Without<Explorer>>)>
+    mut commands: Commands,
+    settings: Res<GuiSettings>,
+    registry: Res<GalaxyRegistry>,
+    mut explorer_query: Query<(&mut Explorer, &mut Transform)>,
+    planet_query: Query<&Transform, (With<Planet>, Without<Explorer>)>
 ) {
     let (explorer_id, planet_id) = (event.id, event.destination);
 
-    // First, get the target planet's transform
-    let mut target_transform = None;
-    for (planet, &transform) in param_set.p1().iter() {
-        if planet.id == planet_id {
-            target_transform = Some(transform);
-            break;
-        }
+    let Some(entity) = registry.explorer(explorer_id) else {
+        return;
+    };
+    let Ok((mut explorer, _)) = explorer_query.get_mut(entity) else {
+        return;
+    };
+
+    if registry.planet(planet_id).is_none() {
+        warn!("explorer tried to move to planet that doesn't exist ({})", planet_id);
+        return;
     }
 
-    // Then, update the explorer
-    for (mut explorer, mut transform) in param_set.p0().iter_mut() {
-        if explorer.id == explorer_id {
-            match target_transform {
-                Some(target) => {
-                    // semantically move the explorer
-                    explorer.current_planet = planet_id;
-                    // graphically move the explorer
-                    *transform = Transform::from_translation(Vec3 {
-                        x: target.translation.x + explorer.position_offset.0,
-                        y: target.translation.y + explorer.position_offset.1,
-                        z: 3.
-                    });
-                }
-                None => {
-                    warn!("explorer tried to move to planet that doesn't exist ({})", planet_id);
-                }
+    // semantically move the explorer
+    let origin = explorer.current_planet;
+    explorer.current_planet = planet_id;
+
+    // graphically move the explorer along the edge, making room for it
+    // on the new planet and closing the gap on the old one.
+    // A move arriving mid-travel starts from wherever the explorer is
+    let travel_seconds = settings.tick_seconds * settings.travel_fraction;
+    let from = planet_transform(&registry, &planet_query, origin).map(|t| t.translation);
+    let traveller = Some((entity, from));
+    arrange_ring(&mut commands, travel_seconds, planet_id, traveller, &registry, &mut explorer_query, &planet_query);
+    arrange_ring(&mut commands, travel_seconds, origin, None, &registry, &mut explorer_query, &planet_query);
+}
+
+/// Spreads the explorers visiting a planet on a ring around it,
+/// gliding there in `travel_seconds` if they aren't in place yet.
+/// The `traveller` that just arrived goes through the centre of the planet
+/// it left, if still on screen, and of this one, so it follows their edge
+fn arrange_ring(
+    commands: &mut Commands,
+    travel_seconds: f32,
+    planet_id: u32,
+    traveller: Option<(Entity, Option<Vec3>)>,
+    registry: &GalaxyRegistry,
+    explorer_query: &mut Query<(&mut Explorer, &mut Transform)>,
+    planet_query: &Query<&Transform, (With<Planet>, Without<Explorer>)>
+) {
+    let Some(planet) = planet_transform(registry, planet_query, planet_id) else {
+        return;
+    };
+
+    let mut guests: Vec<(u32, Entity)> = registry
+        .explorers
+        .iter()
+        .filter(|&(_, &e)| explorer_query.get(e).is_ok_and(|(exp, _)| exp.current_planet == planet_id))
+        .map(|(&id, &e)| (id, e))
+        .collect();
+    guests.sort_unstable();
+
+    let count = guests.len();
+    for (index, (_, entity)) in guests.into_iter().enumerate() {
+        let Ok((mut explorer, mut transform)) = explorer_query.get_mut(entity) else {
+            continue;
+        };
+
+        let offset = ring_offset(index, count);
+        explorer.position_offset = (offset.x, offset.y);
+
+        let end = (planet.translation.truncate() + offset).extend(3.);
+        if transform.translation == end {
+            continue;
+        }
+
+        if travel_seconds > 0. {
+            let mut stops = vec![transform.translation];
+            if let Some((traveller, from)) = traveller
+                && traveller == entity
+            {
+                stops.extend(from.map(|from| from.with_z(3.)));
+                stops.push(planet.translation.with_z(3.));
             }
+            stops.push(end);
+            commands.entity(entity).insert(TweenAnim::new(path_tween(&stops, travel_seconds)));
+        } else {
+            transform.translation = end;
         }
     }
 }
 
+/// Glides through every one of `stops` in `seconds`, at the same speed all along
+fn path_tween(stops: &[Vec3], seconds: f32) -> Sequence {
+    let legs: Vec<(Vec3, Vec3)> = stops.windows(2).map(|leg| (leg[0], leg[1])).filter(|(a, b)| a != b).collect();
+    let length: f32 = legs.iter().map(|(a, b)| a.distance(*b)).sum();
+
+    Sequence::new(legs.into_iter().map(|(start, end)| {
+        Tween::new(
+            EaseFunction::Linear,
+            Duration::from_secs_f32(seconds * start.distance(end) / length),
+            TransformPositionLens { start, end }
+        )
+    }))
+}
+
+/// Drops a fading dot behind every explorer on the move
+pub(crate) fn drop_trail(
+    mut commands: Commands,
+    explorers: Query<(Entity, &Explorer, &Transform), Changed<Transform>>,
+    mut removed: RemovedComponents<Explorer>,
+    mut last_dots: Local<HashMap<Entity, Vec3>>
+) {
+    // forget the explorers that are gone
+    for entity in removed.read() {
+        last_dots.remove(&entity);
+    }
+
+    for (entity, explorer, transform) in &explorers {
+        let last_dot = last_dots.entry(entity).or_insert(transform.translation);
+        if last_dot.distance(transform.translation) < TRAIL_SPACING {
+            continue;
+        }
+
+        *last_dot = transform.translation;
+        commands.spawn((
+            TrailDot { age: 0. },
+            Sprite { color: explorer.tint, custom_size: Some(Vec2::splat(TRAIL_DOT_SIZE)), ..default() },
+            Transform::from_translation(transform.translation.with_z(2.5))
+        ));
+    }
+}
+
+pub(crate) fn fade_trail(
+    mut commands: Commands,
+    time: Res<Time>,
+    speed: Res<SimulationSpeed>,
+    mut dots: Query<(Entity, &mut TrailDot, &mut Sprite)>
+) {
+    for (entity, mut dot, mut sprite) in &mut dots {
+        dot.age += time.delta_secs() * **speed;
+
+        if dot.age >= TRAIL_SECONDS {
+            commands.entity(entity).despawn();
+        } else {
+            sprite.color.set_alpha(1. - dot.age / TRAIL_SECONDS);
+        }
+    }
+}
+
+pub fn basic_resource_generated(
+    event: On<BasicResEvent>,
+    mut commands: Commands,
+    settings: Res<GuiSettings>,
+    registry: Res<GalaxyRegistry>,
+    explorer_query: Query<&Transform, With<Explorer>>
+) {
+    let (color, glyph) = match event.resource {
+        BasicResourceType::Oxygen => (Color::srgb(0.55, 0.8, 1.), "O"),
+        BasicResourceType::Hydrogen => (Color::srgb(0.9, 0.9, 0.95), "H"),
+        BasicResourceType::Carbon => (Color::srgb(0.5, 0.5, 0.5), "C"),
+        BasicResourceType::Silicon => (Color::srgb(0.8, 0.7, 0.5), "Si")
+    };
+
+    // basic resources are squares
+    let icon = Sprite::from_color(color, Vec2::splat(RESOURCE_ICON_SIZE));
+    spawn_resource_popup(&mut commands, &settings, &registry, explorer_query, event.id, (icon, glyph), 0.);
+}
+
+pub fn complex_resource_generated(
+    event: On<ComplexResEvent>,
+    mut commands: Commands,
+    settings: Res<GuiSettings>,
+    registry: Res<GalaxyRegistry>,
+    explorer_query: Query<&Transform, With<Explorer>>
+) {
+    let (color, glyph) = match event.resource {
+        ComplexResourceType::Diamond => (Color::srgb(0.75, 0.95, 1.), "D"),
+        ComplexResourceType::Water => (Color::srgb(0.3, 0.55, 1.), "W"),
+        ComplexResourceType::Life => (Color::srgb(0.45, 0.9, 0.45), "L"),
+        ComplexResourceType::Robot => (Color::srgb(0.7, 0.7, 0.8), "R"),
+        ComplexResourceType::Glucose => (Color::srgb(1., 0.84, 0.4), "G"),
+        ComplexResourceType::AIPartner => (Color::srgb(0.85, 0.5, 1.), "AI")
+    };
+
+    // complex resources are diamonds
+    let icon = Sprite::from_color(color, Vec2::splat(RESOURCE_ICON_SIZE));
+    spawn_resource_popup(&mut commands, &settings, &registry, explorer_query, event.id, (icon, glyph), FRAC_PI_4);
+}
+
+/// Floats the icon of a resource, with its glyph on top, up from the explorer
+/// that generated it, the popup is despawned along with the celestials once the tween ends
+fn spawn_resource_popup(
+    commands: &mut Commands,
+    settings: &GuiSettings,
+    registry: &GalaxyRegistry,
+    explorer_query: Query<&Transform, With<Explorer>>,
+    explorer_id: u32,
+    (icon, glyph): (Sprite, &str),
+    angle: f32
+) {
+    let Some(transform) = registry.explorer(explorer_id).and_then(|e| explorer_query.get(e).ok()) else {
+        warn!("explorer {} generated a resource but it isn't on screen", explorer_id);
+        return;
+    };
+
+    let start = transform.translation.with_z(4.) + Vec3::Y * EXPLORER_SIZE / 2.;
+
+    let tween = Tween::new(
+        EaseFunction::QuadraticOut,
+        Duration::from_secs_f32(settings.tick_seconds / 2.),
+        TransformPositionLens { start, end: start + Vec3::Y * RESOURCE_POPUP_RISE }
+    )
+    .with_cycle_completed_event(true);
+
+    commands.spawn((
+        ResourcePopup,
+        icon,
+        Transform::from_translation(start).with_rotation(Quat::from_rotation_z(angle)),
+        TweenAnim::new(tween),
+        // the glyph stays upright on the rotated icons
+        children![(
+            Text2d::new(glyph),
+            TextFont { font_size: 9., ..default() },
+            TextColor(Color::BLACK),
+            Transform::from_xyz(0., 0., 0.1).with_rotation(Quat::from_rotation_z(-angle))
+        )]
+    ));
+}
+
+/// Text of the [`UiExplorerText::ResourceBag`] field
+fn bag_text(explorer_info: &ExplorerInfo) -> String { format!("Bag: {:?}", explorer_info.bag) }
+
 //TODO run this function at every tick, not every frame
 pub(crate) fn despawn_celestial(
     mut commands: Commands,
@@ -259,6 +754,10 @@ pub(crate) fn despawn_celestial(
                     commands.trigger(PlanetDespawn { planet_id: c.planet_id });
                 }
             }
+
+            // the body lingers a little, crashing into or sinking in the planet
+            commands.entity(event.anim_entity).remove::<Celestial>().insert(Impact::new(c.kind));
+            continue;
         }
 
         commands.entity(event.anim_entity).despawn();
@@ -267,39 +766,48 @@ pub(crate) fn despawn_celestial(
 
 pub(crate) fn choose_on_click(
     click: On<Pointer<Click>>,
-    mut params: ParamSet<(Query<(&mut Sprite, &Planet)>, Query<(&mut Sprite, &Explorer)>)>,
+    planets: Query<&Planet>,
+    explorers: Query<&Explorer>,
     mut chosen_entity: ResMut<EntityClickRes>
 ) {
     info!("Picking event was triggered");
 
-    //reset all sprite dimensions to normal
-    for (mut sprite, _) in &mut params.p0() {
-        sprite.custom_size = Some(Vec2::splat(PLANET_RAD * 2.));
-    }
-
-    for (mut sprite, _) in &mut params.p1() {
-        sprite.custom_size = Some(Vec2::splat(EXPLORER_SIZE));
-    }
-
-    if let Ok((mut sprite, planet)) = params.p0().get_mut(click.entity) {
+    if let Ok(planet) = planets.get(click.entity) {
         info!("picked info for planet {}", planet.id);
-        // make sprite slightly bigger
-        sprite.custom_size = Some(Vec2::splat(PLANET_RAD * 2.5));
-
         chosen_entity.planet = Some(planet.id);
         chosen_entity.explorer = None;
     }
 
-    if let Ok((mut sprite, explorer)) = params.p1().get_mut(click.entity) {
+    if let Ok(explorer) = explorers.get(click.entity) {
         info!("picked info for explorer {}", explorer.id);
-        // make sprite slightly bigger
-        sprite.custom_size = Some(Vec2::splat(EXPLORER_SIZE * 1.5));
-
         chosen_entity.explorer = Some(explorer.id);
         chosen_entity.planet = None;
     }
 }
 
+/// Makes the sprite of the selected planet or explorer slightly bigger,
+/// whether it was clicked in the galaxy or in the log.
+/// Sprites spawned again by a jump in the timeline get it back too.
+pub(crate) fn highlight_selection(
+    chosen_entity: Res<EntityClickRes>,
+    registry: Res<GalaxyRegistry>,
+    mut params: ParamSet<(Query<(&mut Sprite, &Planet)>, Query<(&mut Sprite, &Explorer)>)>
+) {
+    if !chosen_entity.is_changed() && !registry.is_changed() {
+        return;
+    }
+
+    for (mut sprite, planet) in &mut params.p0() {
+        let scale = if chosen_entity.planet == Some(planet.id) { 2.5 } else { 2. };
+        sprite.custom_size = Some(Vec2::splat(PLANET_RAD * scale));
+    }
+
+    for (mut sprite, explorer) in &mut params.p1() {
+        let scale = if chosen_entity.explorer == Some(explorer.id) { 1.5 } else { 1. };
+        sprite.custom_size = Some(Vec2::splat(EXPLORER_SIZE * scale));
+    }
+}
+
 pub(crate) fn update_selected_entity(
     selected_entity: Res<EntityClickRes>,
     planet_status: Res<PlanetInfoRes>,
@@ -307,7 +815,7 @@ pub(crate) fn update_selected_entity(
     mut params: ParamSet<(Query<(&mut Text, &UiPlanetText)>, Query<(&mut Text, &UiExplorerText)>)>
 ) {
     // exit early if the state is the same to avoid extra computation
-    if !selected_entity.is_changed() && !planet_status.is_changed() {
+    if !selected_entity.is_changed() && !planet_status.is_changed() && !explorer_status.is_changed() {
         return;
     }
 
@@ -370,8 +878,7 @@ pub(crate) fn update_selected_entity(
                         **text = format!("Visiting planet {}", explorer_info.current_planet_id);
                     }
                     UiExplorerText::ResourceBag => {
-                        let bag = &explorer_info.bag;
-                        **text = format!("Bag: {:?}", bag);
+                        **text = bag_text(explorer_info);
                     }
                 }
             }
@@ -1,17 +1,24 @@
-use std::collections::VecDeque;
+use std::time::Duration;
 
 use bevy::prelude::*;
-
-use super::ecs::components::LogText;
-use super::ecs::events::{BasicResEvent, Celestial, CelestialBody, ComplexResEvent, MoveExplorerEvent};
-use super::ecs::resources::{EntityClickRes, ExplorerInfoRes, GalaxySnapshot, GameState, GameTimer, LogTextRes,
-                            OrchestratorResource, PlanetInfoRes};
-use super::types::OrchestratorEvent;
+use bevy_tweening::TweenAnim;
+
+use super::ecs::components::GalaxyEntities;
+use super::ecs::events::{BasicResEvent, Celestial, CelestialBody, ComplexResEvent, MoveExplorerEvent, SunraySent};
+use super::ecs::resources::{CameraTarget, EntityClickRes, EventLog, ExplorerInfoRes, GalaxySnapshot, GameHistory,
+                            GameState, GameStats, GameTimer, GuiSettings, OrchestratorResource, PlanetDeath,
+                            PlanetInfoRes, SessionRng, SimulationSpeed, StepRequest, TickCounter};
+use super::event_log::{LogEntry, LogKind, Severity};
+use super::galaxy::{explorer_ids, respawn_galaxy};
+use super::layout::GalaxyLayout;
+use super::replay::{RecordedEvent, RecordedTick, Recorder, start_recording};
+use super::summary::DeathCause;
+use super::types::{OrchestratorEvent, Status};
 use crate::app::AppConfig;
 use crate::explorers::ExplorerFactory;
 use crate::orchestrator::{Orchestrator, OrchestratorMode};
 
-pub fn setup_orchestrator(mut commands: Commands) {
+pub fn setup_orchestrator(mut commands: Commands, settings: Res<GuiSettings>) {
     let config = AppConfig::get();
 
     let explorers = config.explorers.iter().map(ExplorerFactory::make_from_name).collect();
@@ -27,29 +34,127 @@ pub fn setup_orchestrator(mut commands: Commands) {
         panic!("Failed to initialize orchestrator: {e}");
     }
 
-    let topology = orchestrator.get_topology();
+    let topology = topology_of(&orchestrator);
 
-    let first_string = String::from("Orchestrator has started.\nWelcome to the game!");
+    let first_string = format!("Orchestrator has started (seed {}).\nWelcome to the game!", settings.seed);
 
     let lookup = orchestrator.get_planets_info();
 
     let exp_info = orchestrator.get_explorer_states();
 
+    if settings.starting_state == GameState::Playing {
+        orchestrator.set_mode_auto();
+    }
+
     commands.insert_resource(OrchestratorResource { orchestrator });
 
-    commands.insert_resource(GalaxySnapshot { edges: topology, planet_num: config.number_of_planets as usize });
+    let first_frame = RecordedTick {
+        tick: 0,
+        events: Vec::new(),
+        edges: Some(topology.clone()),
+        planets: lookup,
+        explorers: exp_info
+    };
+
+    insert_game_resources(
+        &mut commands,
+        &settings,
+        GalaxySnapshot { edges: topology, planet_num: config.number_of_planets as usize },
+        vec![first_frame],
+        first_string
+    );
+}
+
+/// Inserts every resource a game needs besides its source of events,
+/// shared by live games and replays. The first frame of `history`
+/// is the state shown when the game starts.
+pub(crate) fn insert_game_resources(
+    commands: &mut Commands,
+    settings: &GuiSettings,
+    snapshot: GalaxySnapshot,
+    history: Vec<RecordedTick>,
+    first_log: String
+) {
+    let first_frame = &history[0];
+
+    let planet_ids = first_frame.planets.iter().map(|(&id, _)| id);
+    commands.insert_resource(GalaxyLayout::new(settings.layout, &snapshot, planet_ids));
+
+    commands.insert_resource(snapshot);
 
-    commands.insert_resource(PlanetInfoRes { map: lookup });
+    commands.insert_resource(PlanetInfoRes { map: first_frame.planets.clone() });
 
-    commands.insert_resource(ExplorerInfoRes { map: exp_info });
+    commands.insert_resource(ExplorerInfoRes { map: first_frame.explorers.clone() });
 
-    commands.insert_resource(GameState::WaitingStart);
+    commands.insert_resource(GameHistory::new(history, settings.history_capacity));
 
-    commands.insert_resource(LogTextRes { text: VecDeque::from([first_string]) });
+    commands.insert_resource(settings.starting_state);
 
-    commands.insert_resource(GameTimer(Timer::from_seconds(AppConfig::get().game_tick_seconds, TimerMode::Repeating)));
+    let mut log = EventLog::new(settings.log_capacity);
+    log.push(LogEntry::new(0, Severity::Info, LogKind::Game, first_log));
+    commands.insert_resource(log);
+
+    commands.insert_resource(GameStats::default());
+
+    commands.insert_resource(GameTimer(Timer::from_seconds(settings.tick_seconds, TimerMode::Repeating)));
 
     commands.insert_resource(EntityClickRes { planet: None, explorer: None });
+
+    commands.insert_resource(TickCounter::default());
+
+    commands.insert_resource(SessionRng::new(settings.seed));
+
+    commands.insert_resource(StepRequest::default());
+}
+
+/// Throws away the current game and sets up a new one,
+/// waiting to be started at the normal speed, with the camera back
+/// on the center of the galaxy. A running recording goes on in a new file.
+pub(crate) fn restart_game(world: &mut World) {
+    let entities: Vec<Entity> = world.query_filtered::<Entity, GalaxyEntities>().iter(world).collect();
+    for entity in entities {
+        world.despawn(entity);
+    }
+
+    // each step needs the resources inserted by the previous one,
+    // run_system_cached applies the commands right away
+    for result in [
+        world.run_system_cached(setup_orchestrator),
+        world.run_system_cached(start_recording),
+        world.run_system_cached(respawn_galaxy)
+    ] {
+        if let Err(e) = result {
+            log::error!("Failed to restart the game: {e}");
+        }
+    }
+
+    // the starting state only applies to the first game
+    if *world.resource::<GameState>() == GameState::Playing {
+        world.resource_mut::<OrchestratorResource>().orchestrator.set_mode_manual();
+    }
+    world.insert_resource(GameState::WaitingStart);
+    world.insert_resource(SimulationSpeed::default());
+    world.insert_resource(CameraTarget(Some((Vec2::ZERO, 1.))));
+}
+
+/// Scales the tick length and every running tween by the [`SimulationSpeed`]
+pub(crate) fn apply_simulation_speed(
+    speed: Res<SimulationSpeed>,
+    settings: Res<GuiSettings>,
+    mut timer: ResMut<GameTimer>,
+    mut anims: Query<&mut TweenAnim>
+) {
+    if speed.is_changed() || timer.is_added() {
+        timer.set_duration(Duration::from_secs_f32(speed.tick_seconds(&settings)));
+    }
+
+    // tweens are created with the durations of a 1x game,
+    // this also catches the ones spawned since the last frame
+    for mut anim in &mut anims {
+        if anim.speed != **speed as f64 {
+            anim.speed = **speed as f64;
+        }
+    }
 }
 
 pub fn game_loop(
@@ -57,21 +162,33 @@ pub fn game_loop(
     mut orchestrator: ResMut<OrchestratorResource>,
     mut planets: ResMut<PlanetInfoRes>,
     mut explorers: ResMut<ExplorerInfoRes>,
+    mut snapshot: ResMut<GalaxySnapshot>,
     mut timer: ResMut<GameTimer>,
-    log_text: ResMut<LogTextRes>,
+    mut ticks: ResMut<TickCounter>,
+    mut history: ResMut<GameHistory>,
+    mut recorder: Option<ResMut<Recorder>>,
+    mut step: ResMut<StepRequest>,
+    mut log: ResMut<EventLog>,
+    mut stats: ResMut<GameStats>,
     state: Res<GameState>,
     time: Res<Time>
 ) {
     match *state {
-        GameState::Playing => {
-            timer.tick(time.delta());
-
-            if timer.is_finished() {
+        GameState::Playing | GameState::Paused | GameState::WaitingStart => {
+            // while paused, a tick is played only if a single step was requested
+            let tick_now = if *state == GameState::Playing {
+                timer.tick(time.delta());
+                timer.is_finished()
+            } else {
+                std::mem::take(&mut step.0)
+            };
+
+            if tick_now {
                 println!("ENTERED TIMER");
 
                 let events = orchestrator.orchestrator.get_gui_events_buffer().drain_events();
 
-                handle_tick(&mut commands, events, log_text);
+                handle_tick(&mut commands, &events, **ticks + 1, &mut log, &mut stats);
 
                 // launch either an asteroid or a sunray with a random choice (and let the explorers play)
                 if let Err(e) = orchestrator.orchestrator.manual_step() {
@@ -82,6 +199,19 @@ pub fn game_loop(
                 // update the planet state map after the events occurred
                 planets.as_mut().map = orchestrator.orchestrator.get_planets_info();
                 explorers.as_mut().map = orchestrator.orchestrator.get_explorer_states();
+                let edges = refresh_topology(&orchestrator, &mut snapshot);
+
+                **ticks += 1;
+
+                let frame = new_frame(**ticks, &events, edges, &planets, &explorers);
+                save_frame(&mut commands, &mut history, recorder.as_deref_mut(), frame);
+
+                let last =
+                    last_frame(&mut commands, &mut orchestrator, **ticks, &planets, &explorers, &mut log, &mut stats);
+                if let Some(frame) = last {
+                    save_frame(&mut commands, &mut history, recorder.as_deref_mut(), frame);
+                }
+                finish_if_over(&mut commands, &planets, &explorers, **ticks, &mut log);
 
                 println!("EXITING TIMER");
                 timer.reset();
@@ -91,9 +221,10 @@ pub fn game_loop(
             //if there are manually inputted events, run those immediately
             //else, keep going
 
+            let mut events = Vec::new();
             if orchestrator.orchestrator.get_gui_events_buffer().has_events() {
-                let events = orchestrator.orchestrator.get_gui_events_buffer().drain_events();
-                handle_tick(&mut commands, events, log_text);
+                events = orchestrator.orchestrator.get_gui_events_buffer().drain_events();
+                handle_tick(&mut commands, &events, **ticks, &mut log, &mut stats);
             }
 
             // Process the manual commands sent by the user
@@ -105,68 +236,213 @@ pub fn game_loop(
             // update the planet state map after the events occurred
             planets.as_mut().map = orchestrator.orchestrator.get_planets_info();
             explorers.as_mut().map = orchestrator.orchestrator.get_explorer_states();
+            let edges = refresh_topology(&orchestrator, &mut snapshot);
+
+            // manual actions don't advance the tick, they are saved along with the last one
+            if !events.is_empty() || edges.is_some() {
+                let frame = new_frame(**ticks, &events, edges, &planets, &explorers);
+                save_frame(&mut commands, &mut history, recorder.as_deref_mut(), frame);
+            }
+
+            // a manual asteroid can be the last straw
+            let last =
+                last_frame(&mut commands, &mut orchestrator, **ticks, &planets, &explorers, &mut log, &mut stats);
+            if let Some(frame) = last {
+                save_frame(&mut commands, &mut history, recorder.as_deref_mut(), frame);
+            }
+            finish_if_over(&mut commands, &planets, &explorers, **ticks, &mut log);
         }
         _ => {}
     }
 }
 
-fn handle_tick(commands: &mut Commands, events: Vec<OrchestratorEvent>, mut log_text: ResMut<LogTextRes>) {
+/// Why the game can't go on, if every planet is dead
+/// or none of the explorers is running anymore
+pub(crate) fn game_over(planets: &PlanetInfoRes, explorers: &ExplorerInfoRes) -> Option<&'static str> {
+    // explorers die along with the planet they are visiting
+    let running = explorer_ids()
+        .filter_map(|id| explorers.map.get(&id))
+        .any(|info| info.status == Status::Running && planets.map.get_status(&info.current_planet_id) != Status::Dead);
+
+    if planets.map.iter().all(|(_, info)| info.status == Status::Dead) {
+        Some("every planet is dead")
+    } else if !running {
+        Some("every explorer has stopped")
+    } else {
+        None
+    }
+}
+
+/// Nothing drains the events buffer once the game is over,
+/// so the events of the step that ended it are handled here,
+/// to be saved in one last frame of the same tick
+fn last_frame(
+    commands: &mut Commands,
+    orchestrator: &mut OrchestratorResource,
+    tick: u32,
+    planets: &PlanetInfoRes,
+    explorers: &ExplorerInfoRes,
+    log: &mut EventLog,
+    stats: &mut GameStats
+) -> Option<RecordedTick> {
+    game_over(planets, explorers)?;
+
+    let events = orchestrator.orchestrator.get_gui_events_buffer().drain_events();
+    if events.is_empty() {
+        return None;
+    }
+
+    handle_tick(commands, &events, tick, log, stats);
+    Some(new_frame(tick, &events, None, planets, explorers))
+}
+
+/// Stops playing ticks once the game is over,
+/// the summary overlay then shows up
+pub(crate) fn finish_if_over(
+    commands: &mut Commands,
+    planets: &PlanetInfoRes,
+    explorers: &ExplorerInfoRes,
+    tick: u32,
+    log: &mut EventLog
+) {
+    if let Some(reason) = game_over(planets, explorers) {
+        info!("game over at tick {tick}: {reason}");
+        log.push(LogEntry::new(tick, Severity::Critical, LogKind::Game, format!("game over: {reason}")));
+        commands.insert_resource(GameState::Finished);
+    }
+}
+
+/// Reads the links of the galaxy again, since planets dying or the orchestrator
+/// may change them. The snapshot is only touched if they did change,
+/// in which case the new links are returned.
+fn refresh_topology(
+    orchestrator: &OrchestratorResource,
+    snapshot: &mut ResMut<GalaxySnapshot>
+) -> Option<Vec<(u32, u32)>> {
+    let edges = topology_of(&orchestrator.orchestrator);
+    if snapshot.edges == edges {
+        return None;
+    }
+
+    info!("the topology of the galaxy has changed");
+    snapshot.edges.clone_from(&edges);
+    Some(edges)
+}
+
+/// Links of the galaxy, each one as `(lower id, higher id)` and sorted,
+/// so the same links in another order compare equal
+fn topology_of(orchestrator: &Orchestrator) -> Vec<(u32, u32)> {
+    let mut edges: Vec<(u32, u32)> =
+        orchestrator.get_topology().into_iter().map(|(a, b)| (a.min(b), a.max(b))).collect();
+    edges.sort_unstable();
+    edges.dedup();
+    edges
+}
+
+/// State reached after handling `events`
+fn new_frame(
+    tick: u32,
+    events: &[OrchestratorEvent],
+    edges: Option<Vec<(u32, u32)>>,
+    planets: &PlanetInfoRes,
+    explorers: &ExplorerInfoRes
+) -> RecordedTick {
+    RecordedTick {
+        tick,
+        events: events.iter().map(RecordedEvent::from).collect(),
+        edges,
+        planets: planets.map.clone(),
+        explorers: explorers.map.clone()
+    }
+}
+
+/// Adds a frame to the history,
+/// and writes it to the recording if there is one
+fn save_frame(
+    commands: &mut Commands,
+    history: &mut GameHistory,
+    recorder: Option<&mut Recorder>,
+    frame: RecordedTick
+) {
+    // drop the recorder if the file can't be written anymore
+    if let Some(recorder) = recorder
+        && let Err(e) = recorder.record_tick(&frame)
+    {
+        log::error!("Failed to record tick {}, recording stopped: {e}", frame.tick);
+        commands.remove_resource::<Recorder>();
+    }
+
+    history.push(frame);
+}
+
+pub(crate) fn handle_tick(
+    commands: &mut Commands,
+    events: &[OrchestratorEvent],
+    tick: u32,
+    log: &mut EventLog,
+    stats: &mut GameStats
+) {
     for ev in events {
-        match ev {
+        match *ev {
             OrchestratorEvent::PlanetDestroyed { planet_id } => {
                 // handle the destruction of a planet
                 info!("game-loop: planet {} has died, ", planet_id);
-                update_logs(&mut log_text, format!("planet {} died!\n", planet_id));
+                let cause = match stats.last_celestial.get(&planet_id) {
+                    Some(CelestialBody::Asteroid) => DeathCause::Asteroid,
+                    _ => DeathCause::Unknown
+                };
+                stats.deaths.insert(planet_id, PlanetDeath { tick, cause });
+                log.push(
+                    LogEntry::new(tick, Severity::Critical, LogKind::Death, format!("planet {} died!", planet_id))
+                        .with_planet(planet_id)
+                );
             }
             OrchestratorEvent::SunrayReceived { planet_id } => {
                 info!("game-loop: planet {} got a sunray (UI update), ", planet_id);
                 commands.trigger(Celestial { planet_id, kind: CelestialBody::Sunray });
-                update_logs(&mut log_text, format!("planet {} received a sunray\n", planet_id));
+                let text = format!("planet {} received a sunray", planet_id);
+                log.push(LogEntry::new(tick, Severity::Info, LogKind::Sunray, text).with_planet(planet_id));
             }
             OrchestratorEvent::SunraySent { planet_id } => {
                 info!("game-loop: planet {} should get a sunray, ", planet_id);
-                // TODO only log to screen, nothing changes in the GUI
+                commands.trigger(SunraySent { planet_id });
+                stats.at(tick).sunrays += 1;
+                stats.last_celestial.insert(planet_id, CelestialBody::Sunray);
+                let text = format!("sunray sent to planet {}", planet_id);
+                log.push(LogEntry::new(tick, Severity::Info, LogKind::Sunray, text).with_planet(planet_id));
             }
             OrchestratorEvent::AsteroidSent { planet_id } => {
                 info!("game-loop: planet {} should get an asteroid, ", planet_id);
                 commands.trigger(Celestial { planet_id, kind: CelestialBody::Asteroid });
-                update_logs(&mut log_text, format!("planet {} received an asteroid\n", planet_id));
+                stats.at(tick).asteroids += 1;
+                stats.last_celestial.insert(planet_id, CelestialBody::Asteroid);
+                let text = format!("planet {} received an asteroid", planet_id);
+                log.push(LogEntry::new(tick, Severity::Warning, LogKind::Asteroid, text).with_planet(planet_id));
             }
             OrchestratorEvent::ExplorerMoved { explorer_id, destination } => {
                 info!("game-loop: explorer {} has moved to planet {}", explorer_id, destination);
                 commands.trigger(MoveExplorerEvent { id: explorer_id, destination });
+                let text = format!("explorer {} moved to planet {}", explorer_id, destination);
+                log.push(
+                    LogEntry::new(tick, Severity::Info, LogKind::Move, text)
+                        .with_explorer(explorer_id)
+                        .with_planet(destination)
+                );
             }
             OrchestratorEvent::BasicResourceGenerated { explorer_id, resource } => {
                 info!("game-loop: explorer {} has generated basic resource {:?}", explorer_id, resource);
                 commands.trigger(BasicResEvent { id: explorer_id, resource });
+                *stats.at(tick).resources.entry(explorer_id).or_default() += 1;
+                let text = format!("explorer {} generated {:?}", explorer_id, resource);
+                log.push(LogEntry::new(tick, Severity::Info, LogKind::Resource, text).with_explorer(explorer_id));
             }
             OrchestratorEvent::ComplexResourceGenerated { explorer_id, resource } => {
                 info!("game-loop: explorer {} has generated complex resource {:?}", explorer_id, resource);
                 commands.trigger(ComplexResEvent { id: explorer_id, resource });
+                *stats.at(tick).resources.entry(explorer_id).or_default() += 1;
+                let text = format!("explorer {} combined {:?}", explorer_id, resource);
+                log.push(LogEntry::new(tick, Severity::Info, LogKind::Resource, text).with_explorer(explorer_id));
             }
         }
     }
 }
-
-fn update_logs(log_text: &mut ResMut<LogTextRes>, event_to_push: String) { log_text.text.push_front(event_to_push); }
-
-// TODO find a more performant approach.
-// Fine now because time is of the essence
-// and the average game isn't that long, but this
-// allocates a new string everytime a log event
-// happens. That's a lot of memory gone for nothing!
-
-// alternative approach: spawn the log text
-// directly, using commands.spawn()
-pub(crate) fn log_text(logs: ResMut<LogTextRes>, mut log_node: Single<&mut Text, With<LogText>>) {
-    if !logs.is_changed() {
-        return;
-    };
-    let mut bruh = String::new();
-
-    for log_event in logs.text.iter() {
-        bruh += log_event;
-    }
-
-    log_node.0 = bruh;
-}
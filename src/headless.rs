@@ -0,0 +1,63 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+
+use super::ecs::resources::{EventLog, ExplorerInfoRes, GameState, GuiSettings, PlanetInfoRes, TickCounter};
+use super::event_log::LogEntry;
+use super::plugin::GalaxyGuiPlugin;
+use super::types::{ExplorerInfoMap, PlanetInfoMap};
+
+/// What is left of a game simulated by [`run_headless`]
+pub struct HeadlessRun {
+    /// Seed of the GUI choices, see [`GalaxyGuiPlugin::with_seed`]
+    pub seed: u64,
+    /// Ticks actually played, fewer than requested if the orchestrator failed or the game ended
+    pub ticks: u32,
+    /// Same entries shown in the log panel, oldest first,
+    /// up to [`GalaxyGuiPlugin::with_log_capacity`] of them
+    pub log: VecDeque<LogEntry>,
+    pub planets: PlanetInfoMap,
+    pub explorers: ExplorerInfoMap
+}
+
+/// Plays `ticks` orchestrator steps without a window or renderer.
+///
+/// Every `app.update()` but the first one, which has no delta,
+/// advances the virtual clock by exactly one tick,
+/// so a game runs as fast as the orchestrator allows.
+pub fn run_headless(plugin: GalaxyGuiPlugin, ticks: u32) -> HeadlessRun {
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins).add_plugins(plugin.headless());
+
+    let GuiSettings { tick_seconds, seed, .. } = *app.world().resource::<GuiSettings>();
+    let tick = Duration::from_secs_f32(tick_seconds);
+    app.insert_resource(TimeUpdateStrategy::ManualDuration(tick));
+
+    // the virtual clock clamps every delta to 250ms by default
+    let mut time = app.world_mut().resource_mut::<Time<Virtual>>();
+    let max_delta = time.max_delta().max(tick);
+    time.set_max_delta(max_delta);
+
+    app.finish();
+    app.cleanup();
+
+    loop {
+        app.update();
+
+        let world = app.world();
+        if **world.resource::<TickCounter>() >= ticks || *world.resource::<GameState>() != GameState::Playing {
+            break;
+        }
+    }
+
+    let world = app.world_mut();
+    HeadlessRun {
+        seed,
+        ticks: **world.resource::<TickCounter>(),
+        log: world.remove_resource::<EventLog>().map(EventLog::into_entries).unwrap_or_default(),
+        planets: world.remove_resource::<PlanetInfoRes>().expect("orchestrator was never set up").map,
+        explorers: world.remove_resource::<ExplorerInfoRes>().expect("orchestrator was never set up").map
+    }
+}
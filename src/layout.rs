@@ -0,0 +1,340 @@
+use std::collections::{HashMap, VecDeque};
+use std::f32::consts::TAU;
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_tweening::lens::TransformPositionLens;
+use bevy_tweening::{Tween, TweenAnim};
+
+use super::ecs::components::{ButtonActions, Explorer, GhostPlanet, LayoutLabel, Planet};
+use super::ecs::resources::{GalaxySnapshot, GuiSettings};
+use super::utils::constants::{FORCE_ITERATIONS, GALAXY_RADIUS, LAYOUT_ANIMATION_SECONDS};
+use crate::app::AppConfig;
+
+/// How planets are placed in the galaxy view
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum LayoutKind {
+    /// Evenly spaced on a circle
+    #[default]
+    Circle,
+    /// Linked planets attract each other, all the others repel
+    ForceDirected,
+    /// One column per BFS depth, starting from the initial planet
+    Layered
+}
+
+impl LayoutKind {
+    pub fn next(self) -> Self {
+        match self {
+            LayoutKind::Circle => LayoutKind::ForceDirected,
+            LayoutKind::ForceDirected => LayoutKind::Layered,
+            LayoutKind::Layered => LayoutKind::Circle
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LayoutKind::Circle => "circle",
+            LayoutKind::ForceDirected => "force-directed",
+            LayoutKind::Layered => "layered"
+        }
+    }
+}
+
+/// Position of every planet of the game, dead ones included,
+/// recomputed whenever the links change: the circle stays put,
+/// the other layouts move the planets around when a link is cut
+#[derive(Resource)]
+pub(crate) struct GalaxyLayout {
+    pub kind: LayoutKind,
+    planet_ids: Vec<u32>,
+    /// Edges the positions were computed from
+    edges: Vec<(u32, u32)>,
+    positions: HashMap<u32, Vec2>
+}
+
+impl GalaxyLayout {
+    pub fn new(kind: LayoutKind, snapshot: &GalaxySnapshot, planet_ids: impl Iterator<Item = u32>) -> Self {
+        let mut planet_ids: Vec<u32> = planet_ids.collect();
+        planet_ids.sort_unstable();
+
+        let mut layout = Self { kind, planet_ids, edges: snapshot.edges.clone(), positions: HashMap::new() };
+        layout.compute();
+        layout
+    }
+
+    /// Position of a planet in the galaxy view
+    pub fn position(&self, planet_id: u32) -> Vec2 { self.positions.get(&planet_id).copied().unwrap_or_default() }
+
+    pub fn set_kind(&mut self, kind: LayoutKind) {
+        self.kind = kind;
+        self.compute();
+    }
+
+    /// Recomputes the positions if the links changed,
+    /// returns whether they did
+    pub fn set_edges(&mut self, edges: &[(u32, u32)]) -> bool {
+        if self.edges == edges {
+            return false;
+        }
+
+        self.edges = edges.to_vec();
+        self.compute();
+        true
+    }
+
+    fn compute(&mut self) {
+        let positions = match self.kind {
+            LayoutKind::Circle => circle(&self.planet_ids),
+            LayoutKind::ForceDirected => force_directed(&self.planet_ids, &self.edges),
+            LayoutKind::Layered => layered(&self.planet_ids, &self.edges, AppConfig::get().initial_planet_id)
+        };
+
+        self.positions = self.planet_ids.iter().copied().zip(positions).collect();
+    }
+}
+
+fn circle(planet_ids: &[u32]) -> Vec<Vec2> {
+    let planet_num = planet_ids.len() as f32;
+
+    // spawn all the planets in a circle, with even spacing
+    // Tau = 2 * pi, so all the planets go around the circle
+    (0..planet_ids.len())
+        .map(|i| {
+            let angle = TAU * (i as f32) / planet_num;
+            Vec2::new(GALAXY_RADIUS * angle.cos(), GALAXY_RADIUS * angle.sin())
+        })
+        .collect()
+}
+
+/// Fruchterman-Reingold layout, starting from the circle one
+/// so the result only depends on the topology
+fn force_directed(planet_ids: &[u32], edges: &[(u32, u32)]) -> Vec<Vec2> {
+    let mut positions = circle(planet_ids);
+    let n = positions.len();
+    if n < 2 {
+        return positions;
+    }
+
+    let index: HashMap<u32, usize> = planet_ids.iter().enumerate().map(|(i, &id)| (id, i)).collect();
+    let links: Vec<(usize, usize)> =
+        edges.iter().filter_map(|(a, b)| Some((*index.get(a)?, *index.get(b)?))).collect();
+
+    // ideal distance between two planets
+    let k = 2. * GALAXY_RADIUS / (n as f32).sqrt();
+    let mut temperature = GALAXY_RADIUS / 5.;
+
+    for _ in 0..FORCE_ITERATIONS {
+        let mut displacement = vec![Vec2::ZERO; n];
+
+        for i in 0..n {
+            for j in i + 1..n {
+                let delta = positions[i] - positions[j];
+                let distance = delta.length().max(0.01);
+                let repulsion = delta / distance * k * k / distance;
+                displacement[i] += repulsion;
+                displacement[j] -= repulsion;
+            }
+        }
+
+        for &(i, j) in &links {
+            let delta = positions[i] - positions[j];
+            let distance = delta.length().max(0.01);
+            let attraction = delta / distance * distance * distance / k;
+            displacement[i] -= attraction;
+            displacement[j] += attraction;
+        }
+
+        // the temperature limits how far a planet moves, and cools down every step
+        for (position, displacement) in positions.iter_mut().zip(displacement) {
+            *position += displacement.clamp_length_max(temperature);
+        }
+        temperature *= 0.97;
+    }
+
+    fit_to_galaxy(&mut positions);
+    positions
+}
+
+/// Planets at the same BFS depth from `root` share a column,
+/// the ones that can't be reached go in a last column
+fn layered(planet_ids: &[u32], edges: &[(u32, u32)], root: u32) -> Vec<Vec2> {
+    let mut neighbours: HashMap<u32, Vec<u32>> = HashMap::new();
+    for &(a, b) in edges {
+        neighbours.entry(a).or_default().push(b);
+        neighbours.entry(b).or_default().push(a);
+    }
+
+    let mut depths: HashMap<u32, usize> = HashMap::from([(root, 0)]);
+    let mut queue = VecDeque::from([root]);
+    while let Some(planet) = queue.pop_front() {
+        let depth = depths[&planet];
+        for &next in neighbours.get(&planet).into_iter().flatten() {
+            if !depths.contains_key(&next) {
+                depths.insert(next, depth + 1);
+                queue.push_back(next);
+            }
+        }
+    }
+
+    let unreachable = depths.values().max().map_or(0, |d| d + 1);
+    let depth_of = |id: &u32| depths.get(id).copied().unwrap_or(unreachable);
+
+    let mut layers: Vec<Vec<usize>> = Vec::new();
+    for (i, id) in planet_ids.iter().enumerate() {
+        let depth = depth_of(id);
+        if layers.len() <= depth {
+            layers.resize(depth + 1, Vec::new());
+        }
+        layers[depth].push(i);
+    }
+    layers.retain(|layer| !layer.is_empty());
+
+    let mut positions = vec![Vec2::ZERO; planet_ids.len()];
+    let columns = layers.len().max(2) as f32 - 1.;
+
+    for (column, layer) in layers.iter().enumerate() {
+        let x = if layers.len() == 1 { 0. } else { -GALAXY_RADIUS + 2. * GALAXY_RADIUS * column as f32 / columns };
+
+        for (row, &i) in layer.iter().enumerate() {
+            let y = GALAXY_RADIUS - 2. * GALAXY_RADIUS * (row as f32 + 0.5) / layer.len() as f32;
+            positions[i] = Vec2::new(x, y);
+        }
+    }
+
+    positions
+}
+
+/// Centers the positions and scales them to the galaxy radius
+fn fit_to_galaxy(positions: &mut [Vec2]) {
+    let center = positions.iter().sum::<Vec2>() / positions.len() as f32;
+    let furthest = positions.iter().map(|p| p.distance(center)).fold(0., f32::max);
+    let scale = if furthest > 0. { GALAXY_RADIUS / furthest } else { 1. };
+
+    for position in positions {
+        *position = (*position - center) * scale;
+    }
+}
+
+pub(crate) fn layout_action(
+    action_query: Query<(&Interaction, &ButtonActions), (Changed<Interaction>, With<Button>)>,
+    mut layout: ResMut<GalaxyLayout>,
+    mut settings: ResMut<GuiSettings>
+) {
+    for (&interaction, action) in &action_query {
+        if interaction == Interaction::Pressed
+            && let ButtonActions::CycleLayout = action
+        {
+            let kind = layout.kind.next();
+            layout.set_kind(kind);
+            // a restarted game keeps the layout
+            settings.layout = kind;
+            info!("layout set to {}", kind.name());
+        }
+    }
+}
+
+/// Follows the links of the galaxy
+pub(crate) fn update_layout(snapshot: Res<GalaxySnapshot>, mut layout: ResMut<GalaxyLayout>) {
+    if snapshot.is_changed() && !snapshot.is_added() {
+        // only touch the layout if the links changed,
+        // so the sprites aren't moved for nothing
+        let edges = &snapshot.edges;
+        if layout.bypass_change_detection().set_edges(edges) {
+            layout.set_changed();
+        }
+    }
+}
+
+/// Moves the planets and their explorers to their new positions,
+/// the edges follow in [`follow_planets`](super::galaxy::follow_planets)
+pub(crate) fn apply_layout(
+    mut commands: Commands,
+    layout: Res<GalaxyLayout>,
+    settings: Res<GuiSettings>,
+    mut planets: Query<(Entity, &Planet, &mut Transform), Without<Explorer>>,
+    mut ghosts: Query<(Entity, &GhostPlanet, &mut Transform), (Without<Planet>, Without<Explorer>)>,
+    mut explorers: Query<(Entity, &Explorer, &mut Transform), (Without<Planet>, Without<GhostPlanet>)>
+) {
+    // a new layout comes with a new galaxy, already in place
+    if !layout.is_changed() || layout.is_added() {
+        return;
+    }
+
+    let mut move_to = |entity: Entity, transform: &mut Transform, target: Vec2| {
+        let end = target.extend(transform.translation.z);
+
+        if settings.animate_layout {
+            let tween = Tween::new(
+                EaseFunction::CubicInOut,
+                Duration::from_secs_f32(LAYOUT_ANIMATION_SECONDS),
+                TransformPositionLens { start: transform.translation, end }
+            );
+            commands.entity(entity).insert(TweenAnim::new(tween));
+        } else {
+            transform.translation = end;
+        }
+    };
+
+    for (entity, planet, mut transform) in &mut planets {
+        move_to(entity, &mut transform, layout.position(planet.id));
+    }
+
+    for (entity, ghost, mut transform) in &mut ghosts {
+        move_to(entity, &mut transform, layout.position(ghost.id));
+    }
+
+    for (entity, explorer, mut transform) in &mut explorers {
+        let offset = Vec2::new(explorer.position_offset.0, explorer.position_offset.1);
+        move_to(entity, &mut transform, layout.position(explorer.current_planet) + offset);
+    }
+}
+
+pub(crate) fn update_layout_label(layout: Res<GalaxyLayout>, mut label: Single<&mut Text, With<LayoutLabel>>) {
+    if layout.is_changed() {
+        label.0 = format!("Layout: {}", layout.kind.name());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn circle_spaces_planets_on_the_galaxy_radius() {
+        let positions = circle(&[1, 2, 3, 4]);
+
+        assert_eq!(positions.len(), 4);
+        for position in &positions {
+            assert!((position.length() - GALAXY_RADIUS).abs() < 1e-3);
+        }
+        // consecutive planets are a quarter turn apart
+        let side = GALAXY_RADIUS * 2f32.sqrt();
+        for (a, b) in positions.iter().zip(positions.iter().cycle().skip(1)) {
+            assert!((a.distance(*b) - side).abs() < 1e-3);
+        }
+    }
+
+    #[test]
+    fn force_directed_pulls_linked_planets_together() {
+        let positions = force_directed(&[1, 2, 3, 4], &[(1, 2), (3, 4)]);
+
+        assert!(positions[0].distance(positions[1]) < positions[0].distance(positions[2]));
+        assert!(positions[2].distance(positions[3]) < positions[1].distance(positions[3]));
+
+        let furthest = positions.iter().map(|p| p.length()).fold(0., f32::max);
+        assert!((furthest - GALAXY_RADIUS).abs() < 1.);
+    }
+
+    #[test]
+    fn layered_puts_one_depth_per_column() {
+        let positions = layered(&[1, 2, 3, 4], &[(1, 2), (2, 3)], 1);
+
+        assert_eq!(positions[0].x, -GALAXY_RADIUS);
+        assert!(positions[0].x < positions[1].x && positions[1].x < positions[2].x);
+        // the unreachable planet goes in the last column
+        assert_eq!(positions[3].x, GALAXY_RADIUS);
+        // a single planet per column sits in the middle of it
+        assert!(positions.iter().all(|p| p.y.abs() < 1e-3));
+    }
+}
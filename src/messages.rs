@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use bevy::prelude::*;
+
+use super::ecs::components::{ButtonActions, MessageFilterLabel, MessageList, MessagePanel};
+use super::ecs::resources::{GameHistory, MessageFilter, PlanetInfoRes};
+use super::galaxy::explorer_ids;
+use super::replay::{RecordedEvent, RecordedTick};
+use super::ui::filter_button;
+use super::utils::constants::{CENTER_PANEL_MARGIN, MAX_MESSAGE_ROWS};
+
+/// One side of a message
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Participant {
+    Orchestrator,
+    Planet(u32),
+    Explorer(u32)
+}
+
+impl fmt::Display for Participant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Participant::Orchestrator => write!(f, "orchestrator"),
+            Participant::Planet(id) => write!(f, "planet {}", id),
+            Participant::Explorer(id) => write!(f, "explorer {}", id)
+        }
+    }
+}
+
+/// Exchange between two participants, derived from an orchestrator event.
+/// The real messages are not reported, so this only tells who was involved
+/// and names the event that came out of it.
+pub(crate) struct DerivedMessage {
+    pub tick: u32,
+    pub sender: Participant,
+    pub receiver: Participant,
+    /// Name of the orchestrator event
+    pub event: String
+}
+
+impl DerivedMessage {
+    fn involves(&self, participant: Participant) -> bool {
+        self.sender == participant || self.receiver == participant
+    }
+}
+
+/// Turns the events of a frame into the exchanges behind them.
+///
+/// The events of a frame come from the step played before the one it holds
+/// the state of, so `start` is where the explorers were when they happened:
+/// two frames back. An explorer generates resources on the planet it is on
+/// at that moment, which is not the one it ends on if it moves afterwards.
+pub(crate) fn frame_messages(frame: &RecordedTick, start: &RecordedTick) -> Vec<DerivedMessage> {
+    // planets the explorers moved to during these events
+    let mut moved: HashMap<u32, u32> = HashMap::new();
+    let location = |moved: &HashMap<u32, u32>, explorer_id: u32| {
+        moved.get(&explorer_id).copied().unwrap_or_else(|| start.explorers.get_current_planet(&explorer_id))
+    };
+
+    frame
+        .events
+        .iter()
+        .map(|event| {
+            let (sender, receiver, event) = match event {
+                RecordedEvent::SunraySent { planet_id } =>
+                    (Participant::Orchestrator, Participant::Planet(*planet_id), "SunraySent".to_string()),
+                RecordedEvent::SunrayReceived { planet_id } =>
+                    (Participant::Planet(*planet_id), Participant::Orchestrator, "SunrayReceived".to_string()),
+                RecordedEvent::AsteroidSent { planet_id } =>
+                    (Participant::Orchestrator, Participant::Planet(*planet_id), "AsteroidSent".to_string()),
+                RecordedEvent::PlanetDestroyed { planet_id } =>
+                    (Participant::Planet(*planet_id), Participant::Orchestrator, "PlanetDestroyed".to_string()),
+                RecordedEvent::ExplorerMoved { explorer_id, destination } => {
+                    moved.insert(*explorer_id, *destination);
+                    (
+                        Participant::Explorer(*explorer_id),
+                        Participant::Orchestrator,
+                        format!("ExplorerMoved({})", destination)
+                    )
+                }
+                RecordedEvent::BasicResourceGenerated { explorer_id, resource } => (
+                    Participant::Explorer(*explorer_id),
+                    Participant::Planet(location(&moved, *explorer_id)),
+                    format!("BasicResourceGenerated({})", resource)
+                ),
+                RecordedEvent::ComplexResourceGenerated { explorer_id, resource } => (
+                    Participant::Explorer(*explorer_id),
+                    Participant::Planet(location(&moved, *explorer_id)),
+                    format!("ComplexResourceGenerated({})", resource)
+                )
+            };
+
+            DerivedMessage { tick: frame.tick, sender, receiver, event }
+        })
+        .collect()
+}
+
+/// Draws the (initially hidden) message inspector
+/// on top of the galaxy view
+pub(crate) fn draw_message_panel(mut commands: Commands) {
+    let panel = (
+        BackgroundColor(Color::Srgba(Srgba { red: 0.12, green: 0.18, blue: 0.24, alpha: 0.9 })),
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(CENTER_PANEL_MARGIN),
+            right: Val::Px(CENTER_PANEL_MARGIN),
+            top: Val::Px(10.),
+            height: Val::Percent(45.),
+            flex_direction: FlexDirection::Column,
+            padding: UiRect::all(Val::Px(10.)),
+            ..default()
+        },
+        Visibility::Hidden,
+        MessagePanel
+    );
+
+    commands.spawn(panel).with_children(|parent| {
+        parent.spawn(Text::new("Explorer Messages"));
+        parent.spawn((
+            Text::new("Derived from the orchestrator events, the messages themselves are not reported"),
+            TextFont { font_size: 11., ..default() }
+        ));
+
+        parent
+            .spawn(Node { flex_direction: FlexDirection::Row, margin: UiRect::vertical(Val::Px(8.)), ..default() })
+            .with_children(|parent| {
+                parent.spawn(filter_button(
+                    "Explorer: all",
+                    MessageFilterLabel::Explorer,
+                    ButtonActions::CycleExplorerFilter
+                ));
+                parent.spawn(filter_button(
+                    "Planet: all",
+                    MessageFilterLabel::Planet,
+                    ButtonActions::CyclePlanetFilter
+                ));
+            });
+
+        parent.spawn((
+            BackgroundColor(Color::Srgba(Srgba { red: 0., green: 0., blue: 0., alpha: 0.6 })),
+            Node { flex_direction: FlexDirection::Column, flex_grow: 1., overflow: Overflow::scroll_y(), ..default() },
+            MessageList
+        ));
+    });
+}
+
+/// Returns the element after `current` in `ids`, wrapping
+/// to `None` (no filter) after the last one
+fn next_filter(current: Option<u32>, ids: &[u32]) -> Option<u32> {
+    match current {
+        None => ids.first().copied(),
+        Some(id) => ids.iter().skip_while(|&&i| i != id).nth(1).copied()
+    }
+}
+
+pub(crate) fn message_panel_action(
+    action_query: Query<(&Interaction, &ButtonActions), (Changed<Interaction>, With<Button>)>,
+    mut panel: Single<&mut Visibility, With<MessagePanel>>,
+    mut filter: ResMut<MessageFilter>,
+    planets: Res<PlanetInfoRes>
+) {
+    for (&interaction, action) in &action_query {
+        if interaction == Interaction::Pressed {
+            match action {
+                ButtonActions::ShowExplorerMessages => {
+                    panel.toggle_visible_hidden();
+                }
+                ButtonActions::CycleExplorerFilter => {
+                    let explorers: Vec<u32> = explorer_ids().collect();
+                    filter.explorer = next_filter(filter.explorer, &explorers);
+                }
+                ButtonActions::CyclePlanetFilter => {
+                    let mut planet_ids: Vec<u32> = planets.map.iter().map(|(&id, _)| id).collect();
+                    planet_ids.sort_unstable();
+                    filter.planet = next_filter(filter.planet, &planet_ids);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Lists the latest messages matching the filter, up to the frame shown.
+/// Nothing is rebuilt while the panel is hidden.
+pub(crate) fn update_message_list(
+    mut commands: Commands,
+    history: Res<GameHistory>,
+    filter: Res<MessageFilter>,
+    panel: Single<Ref<Visibility>, With<MessagePanel>>,
+    list: Single<Entity, With<MessageList>>,
+    mut labels: Query<(&mut Text, &MessageFilterLabel)>
+) {
+    if **panel == Visibility::Hidden || (!history.is_changed() && !filter.is_changed() && !panel.is_changed()) {
+        return;
+    }
+
+    if filter.is_changed() {
+        for (mut text, label) in &mut labels {
+            let (name, value) = match label {
+                MessageFilterLabel::Explorer => ("Explorer", filter.explorer),
+                MessageFilterLabel::Planet => ("Planet", filter.planet)
+            };
+            **text = match value {
+                Some(id) => format!("{}: {}", name, id),
+                None => format!("{}: all", name)
+            };
+        }
+    }
+
+    // newest first, stopping as soon as there are enough rows
+    let frames = &history.frames;
+    let messages: Vec<DerivedMessage> = (0..=history.current)
+        .rev()
+        .flat_map(|i| frame_messages(&frames[i], &frames[i.saturating_sub(2)]).into_iter().rev())
+        .filter(|m| filter.explorer.is_none_or(|id| m.involves(Participant::Explorer(id))))
+        .filter(|m| filter.planet.is_none_or(|id| m.involves(Participant::Planet(id))))
+        .take(MAX_MESSAGE_ROWS)
+        .collect();
+
+    commands.entity(*list).despawn_children();
+    commands.entity(*list).with_children(|parent| {
+        for message in messages {
+            parent.spawn((
+                Text::new(format!(
+                    "[tick {}] {} -> {}: {} (derived)",
+                    message.tick, message.sender, message.receiver, message.event
+                )),
+                TextFont { font_size: 12., ..default() }
+            ));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::types::{ExplorerInfo, ExplorerInfoMap, PlanetInfoMap, Status};
+    use super::*;
+
+    fn frame_with_explorer_on(planet_id: u32, events: Vec<RecordedEvent>) -> RecordedTick {
+        let mut explorers = ExplorerInfoMap::default();
+        explorers.insert(5, ExplorerInfo { status: Status::Running, current_planet_id: planet_id, bag: default() });
+        RecordedTick { tick: 2, events, edges: None, planets: PlanetInfoMap::default(), explorers }
+    }
+
+    #[test]
+    fn resources_are_generated_where_the_explorer_was_at_the_time() {
+        let start = frame_with_explorer_on(1, Vec::new());
+        // the frame holding the events already has the explorer on its destination
+        let frame = frame_with_explorer_on(2, vec![
+            RecordedEvent::BasicResourceGenerated { explorer_id: 5, resource: "Oxygen".to_string() },
+            RecordedEvent::ExplorerMoved { explorer_id: 5, destination: 2 },
+            RecordedEvent::ComplexResourceGenerated { explorer_id: 5, resource: "Water".to_string() }
+        ]);
+
+        let messages = frame_messages(&frame, &start);
+
+        assert_eq!(messages.len(), 3);
+        assert!(messages[0].receiver == Participant::Planet(1));
+        assert!(messages[1].receiver == Participant::Orchestrator);
+        assert!(messages[2].receiver == Participant::Planet(2));
+    }
+}
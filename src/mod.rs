@@ -0,0 +1,26 @@
+mod camera;
+mod celestial;
+mod ecs;
+mod event_log;
+mod export;
+mod galaxy;
+mod game;
+mod headless;
+mod layout;
+mod messages;
+mod plugin;
+mod replay;
+mod stats;
+mod status;
+mod summary;
+mod timeline;
+pub mod types;
+mod ui;
+mod utils;
+
+pub use celestial::CelestialEntry;
+pub use ecs::resources::GameState;
+pub use event_log::{LogEntry, LogKind, Severity};
+pub use headless::{HeadlessRun, run_headless};
+pub use layout::LayoutKind;
+pub use plugin::{GalaxyGuiPlugin, GalaxyGuiSet};
@@ -0,0 +1,386 @@
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use bevy_tweening::TweeningPlugin;
+
+use super::camera::{camera_action, focus_on_double_click, move_camera, pan_camera, zoom_camera};
+use super::celestial::{CelestialEntry, animate_impacts, move_celestial, pulse_pending_sunrays, show_pending_sunray};
+use super::ecs::resources::{CameraTarget, GalaxyRegistry, GameState, GuiSettings, LastClick, LogFilter,
+                            MessageFilter, SimulationSpeed};
+use super::event_log::{log_filter_action, log_row_action, type_log_search, update_log_list};
+use super::export::export_action;
+use super::galaxy::{animate_deaths, basic_resource_generated, complex_resource_generated, despawn_celestial,
+                    destroy_link, draw_topology, drop_trail, fade_trail, follow_planets, highlight_selection,
+                    move_explorer, setup, update_energy_rings, update_selected_entity};
+use super::game::{apply_simulation_speed, game_loop, setup_orchestrator};
+use super::layout::{LayoutKind, apply_layout, layout_action, update_layout, update_layout_label};
+use super::messages::{draw_message_panel, message_panel_action, update_message_list};
+use super::replay::{replay_loop, replay_menu_action, setup_replay, start_recording};
+use super::stats::{draw_stats_panel, record_planet_stats, stats_panel_action, update_stats_panel};
+use super::status::{show_edge_status, show_explorer_status, show_planet_status};
+use super::summary::{draw_summary_overlay, summary_action, update_summary};
+use super::timeline::{draw_timeline, jump_to_frame, leave_review, timeline_action, timeline_click, update_timeline};
+use super::ui::{button_hover, draw_entity_info_menu, draw_game_options_menu, explorer_move_action, game_menu_action,
+                manual_explorer_action, manual_planet_action, on_scroll_handler, populate_dropdown,
+                send_scroll_events, speed_action, update_explorer_buttons_visibility,
+                update_planet_buttons_visibility, update_speed_label};
+use super::utils::assets::load_assets;
+use super::utils::constants::{HISTORY_CAPACITY, LOG_CAPACITY};
+use crate::app::AppConfig;
+
+/// Groups of systems registered by [`GalaxyGuiPlugin`].
+///
+/// The `Update` sets run in the order they are declared,
+/// so external systems can be placed between them.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GalaxyGuiSet {
+    /// Startup: assets, orchestrator, sprites and menus
+    Setup,
+    /// Buttons, dropdowns and mouse input
+    Input,
+    /// Orchestrator ticks and the resources they refresh
+    Simulation,
+    /// Sprites living in the galaxy view
+    Galaxy,
+    /// Side menus, texts and logs
+    Ui
+}
+
+/// Registers every system, observer and resource
+/// needed to run the galaxy GUI.
+///
+/// ```ignore
+/// App::new()
+///     .add_plugins(DefaultPlugins)
+///     .add_plugins(GalaxyGuiPlugin::new().with_tick_seconds(0.5).with_starting_state(GameState::Playing))
+///     .run();
+/// ```
+pub struct GalaxyGuiPlugin {
+    tick_seconds: Option<f32>,
+    window_size: Option<Vec2>,
+    starting_state: GameState,
+    headless: bool,
+    seed: Option<u64>,
+    record_path: Option<PathBuf>,
+    replay_path: Option<PathBuf>,
+    layout: LayoutKind,
+    animate_layout: bool,
+    travel_fraction: f32,
+    ghost_planets: bool,
+    celestial_entry: CelestialEntry,
+    log_capacity: usize,
+    history_capacity: usize,
+    export_dir: PathBuf
+}
+
+impl Default for GalaxyGuiPlugin {
+    fn default() -> Self {
+        Self {
+            tick_seconds: None,
+            window_size: None,
+            starting_state: GameState::WaitingStart,
+            headless: false,
+            seed: None,
+            record_path: None,
+            replay_path: None,
+            layout: LayoutKind::default(),
+            animate_layout: true,
+            travel_fraction: 0.5,
+            ghost_planets: false,
+            celestial_entry: CelestialEntry::default(),
+            log_capacity: LOG_CAPACITY,
+            history_capacity: HISTORY_CAPACITY,
+            export_dir: PathBuf::from(".")
+        }
+    }
+}
+
+impl GalaxyGuiPlugin {
+    pub fn new() -> Self { Self::default() }
+
+    /// Seconds between two orchestrator steps,
+    /// defaults to `AppConfig::game_tick_seconds`
+    pub fn with_tick_seconds(mut self, seconds: f32) -> Self {
+        self.tick_seconds = Some(seconds);
+        self
+    }
+
+    /// Resizes the primary window on startup
+    pub fn with_window_size(mut self, width: f32, height: f32) -> Self {
+        self.window_size = Some(Vec2::new(width, height));
+        self
+    }
+
+    /// State of the game once the orchestrator is ready,
+    /// defaults to [`GameState::WaitingStart`]
+    pub fn with_starting_state(mut self, state: GameState) -> Self {
+        self.starting_state = state;
+        self
+    }
+
+    /// Runs the orchestrator without sprites, menus or tweens,
+    /// so the app only needs `MinimalPlugins`.
+    /// The game starts right away, see [`run_headless`](super::headless::run_headless).
+    pub fn headless(mut self) -> Self {
+        self.headless = true;
+        self.starting_state = GameState::Playing;
+        self
+    }
+
+    /// Seed of the random choices made by the GUI: the resources
+    /// generated by the manual explorer actions and where celestials come from.
+    /// The orchestrator picks sunrays and asteroids on its own, unseeded,
+    /// so only a [recording](Self::with_recording) plays a game back exactly.
+    /// Defaults to the `--seed <n>` command line argument,
+    /// or to a random one if that is missing too.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Records every tick of the game to a JSON Lines file,
+    /// the games restarted afterwards get a `-<n>` suffix
+    pub fn with_recording(mut self, path: impl Into<PathBuf>) -> Self {
+        self.record_path = Some(path.into());
+        self
+    }
+
+    /// Plays back a file written by [`with_recording`](Self::with_recording)
+    /// instead of running an orchestrator. Bags are only recorded as text,
+    /// so the replayed explorers show an empty one
+    pub fn with_replay(mut self, path: impl Into<PathBuf>) -> Self {
+        self.replay_path = Some(path.into());
+        self
+    }
+
+    /// How planets are placed, can also be changed from the game menu.
+    /// Defaults to [`LayoutKind::Circle`]
+    pub fn with_layout(mut self, layout: LayoutKind) -> Self {
+        self.layout = layout;
+        self
+    }
+
+    /// Whether planets glide to their new positions when the layout changes,
+    /// instead of jumping there. Enabled by default
+    pub fn with_layout_animation(mut self, animate: bool) -> Self {
+        self.animate_layout = animate;
+        self
+    }
+
+    /// Part of a tick an explorer takes to travel between two planets,
+    /// clamped between 0 (instant) and 1. Defaults to half a tick
+    pub fn with_travel_fraction(mut self, fraction: f32) -> Self {
+        self.travel_fraction = fraction.clamp(0., 1.);
+        self
+    }
+
+    /// Whether dead planets stay in the galaxy as grey ghosts,
+    /// with dashed links, instead of disappearing. Disabled by default
+    pub fn with_ghost_planets(mut self, ghosts: bool) -> Self {
+        self.ghost_planets = ghosts;
+        self
+    }
+
+    /// Where sunrays and asteroids enter the screen from,
+    /// defaults to [`CelestialEntry::Random`]
+    pub fn with_celestial_entry(mut self, entry: CelestialEntry) -> Self {
+        self.celestial_entry = entry;
+        self
+    }
+
+    /// Entries kept by the event log, the oldest ones are dropped
+    /// past this number. Defaults to 500
+    pub fn with_log_capacity(mut self, capacity: usize) -> Self {
+        self.log_capacity = capacity.max(1);
+        self
+    }
+
+    /// Ticks of a live game the timeline can go back to, the oldest
+    /// ones are dropped past this number, only a summary of them is kept
+    /// for the export. Defaults to 2000
+    pub fn with_history_capacity(mut self, capacity: usize) -> Self {
+        self.history_capacity = capacity.max(2);
+        self
+    }
+
+    /// Directory the "Export log" button writes its JSON Lines
+    /// and CSV files to, defaults to the working directory
+    pub fn with_export_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.export_dir = dir.into();
+        self
+    }
+
+    fn settings(&self) -> GuiSettings {
+        GuiSettings {
+            tick_seconds: self.tick_seconds.unwrap_or_else(|| AppConfig::get().game_tick_seconds),
+            window_size: self.window_size,
+            starting_state: self.starting_state,
+            seed: self.seed.or_else(seed_from_args).unwrap_or_else(rand::random),
+            record_path: self.record_path.clone(),
+            replay_path: self.replay_path.clone(),
+            layout: self.layout,
+            animate_layout: self.animate_layout,
+            travel_fraction: self.travel_fraction,
+            ghost_planets: self.ghost_planets,
+            celestial_entry: self.celestial_entry,
+            log_capacity: self.log_capacity,
+            history_capacity: self.history_capacity,
+            export_dir: self.export_dir.clone()
+        }
+    }
+}
+
+impl Plugin for GalaxyGuiPlugin {
+    fn build(&self, app: &mut App) {
+        if self.headless {
+            // same tick logic, nothing to draw
+            app.insert_resource(self.settings())
+                .add_systems(Startup, (setup_orchestrator, start_recording).chain().in_set(GalaxyGuiSet::Setup))
+                .add_systems(Update, game_loop.in_set(GalaxyGuiSet::Simulation));
+            return;
+        }
+
+        if !app.is_plugin_added::<TweeningPlugin>() {
+            app.add_plugins(TweeningPlugin);
+        }
+
+        // a replay reads its events from the recording instead of an orchestrator
+        let (game_source, game_actions, game_tick) = if self.replay_path.is_some() {
+            (setup_replay.into_configs(), replay_menu_action.into_configs(), replay_loop.into_configs())
+        } else {
+            (
+                (setup_orchestrator, start_recording).chain(),
+                (
+                    game_menu_action,
+                    (manual_planet_action, manual_explorer_action, explorer_move_action).run_if(game_not_finished)
+                )
+                    .into_configs(),
+                (leave_review, game_loop).chain()
+            )
+        };
+
+        app.insert_resource(self.settings())
+            .init_resource::<SimulationSpeed>()
+            .init_resource::<MessageFilter>()
+            .init_resource::<LogFilter>()
+            .init_resource::<CameraTarget>()
+            .init_resource::<LastClick>()
+            .init_resource::<GalaxyRegistry>()
+            .configure_sets(
+                Update,
+                (GalaxyGuiSet::Input, GalaxyGuiSet::Simulation, GalaxyGuiSet::Galaxy, GalaxyGuiSet::Ui).chain()
+            )
+            .add_systems(
+                Startup,
+                (
+                    resize_window,
+                    // every step needs the resources inserted by the previous one
+                    (
+                        load_assets,
+                        game_source,
+                        setup,
+                        draw_game_options_menu,
+                        draw_entity_info_menu,
+                        draw_timeline,
+                        draw_message_panel,
+                        draw_stats_panel,
+                        draw_summary_overlay
+                    )
+                        .chain()
+                )
+                    .in_set(GalaxyGuiSet::Setup)
+            )
+            .add_systems(
+                Update,
+                (
+                    button_hover,
+                    send_scroll_events,
+                    timeline_action,
+                    timeline_click,
+                    speed_action,
+                    message_panel_action,
+                    layout_action,
+                    log_filter_action,
+                    log_row_action,
+                    export_action,
+                    stats_panel_action,
+                    summary_action,
+                    type_log_search,
+                    pan_camera,
+                    zoom_camera,
+                    camera_action,
+                    game_actions
+                )
+                    .in_set(GalaxyGuiSet::Input)
+            )
+            .add_systems(
+                Update,
+                (apply_simulation_speed, game_tick, record_planet_stats)
+                    .chain()
+                    .in_set(GalaxyGuiSet::Simulation)
+            )
+            .add_systems(
+                Update,
+                (
+                    (update_layout, apply_layout, draw_topology, follow_planets).chain(),
+                    despawn_celestial,
+                    move_camera,
+                    drop_trail,
+                    fade_trail,
+                    animate_deaths,
+                    update_energy_rings,
+                    highlight_selection,
+                    animate_impacts,
+                    pulse_pending_sunrays,
+                    (show_planet_status, show_explorer_status, show_edge_status).after(draw_topology)
+                )
+                    .in_set(GalaxyGuiSet::Galaxy)
+            )
+            .add_systems(
+                Update,
+                (
+                    update_selected_entity,
+                    update_log_list,
+                    populate_dropdown,
+                    update_planet_buttons_visibility,
+                    update_explorer_buttons_visibility,
+                    update_timeline,
+                    update_speed_label,
+                    update_message_list,
+                    update_layout_label,
+                    update_stats_panel,
+                    update_summary
+                )
+                    .in_set(GalaxyGuiSet::Ui)
+            )
+            .add_observer(destroy_link)
+            .add_observer(move_celestial)
+            .add_observer(show_pending_sunray)
+            .add_observer(move_explorer)
+            .add_observer(basic_resource_generated)
+            .add_observer(complex_resource_generated)
+            .add_observer(on_scroll_handler)
+            .add_observer(jump_to_frame)
+            .add_observer(focus_on_double_click);
+    }
+}
+
+/// A finished game takes no more manual actions
+fn game_not_finished(state: Res<GameState>) -> bool { *state != GameState::Finished }
+
+/// Reads the value following `--seed`, if any
+fn seed_from_args() -> Option<u64> {
+    let mut args = std::env::args().skip_while(|arg| arg != "--seed").skip(1);
+
+    let arg = args.next()?;
+    arg.parse()
+        .inspect_err(|e| warn!("ignoring invalid seed {arg}: {e}"))
+        .ok()
+}
+
+fn resize_window(settings: Res<GuiSettings>, mut window: Single<&mut Window, With<PrimaryWindow>>) {
+    if let Some(size) = settings.window_size {
+        window.resolution.set(size.x, size.y);
+    }
+}
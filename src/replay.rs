@@ -0,0 +1,535 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use common_game::components::resource::BasicResourceType::{self, Carbon, Hydrogen, Oxygen, Silicon};
+use common_game::components::resource::ComplexResourceType::{self, AIPartner, Diamond, Glucose, Life, Robot, Water};
+use serde::{Deserialize, Serialize};
+
+use super::ecs::components::ButtonActions;
+use super::ecs::events::JumpToFrame;
+use super::ecs::resources::{EventLog, ExplorerInfoRes, GalaxySnapshot, GameHistory, GameState, GameStats, GameTimer,
+                            GuiSettings, PlanetInfoRes, StepRequest, TickCounter};
+use super::event_log::{LogEntry, LogKind, Severity};
+use super::galaxy::explorer_ids;
+use super::game::{finish_if_over, handle_tick, insert_game_resources};
+use super::types::{ExplorerInfo, ExplorerInfoMap, OrchestratorEvent, PlanetInfo, PlanetInfoMap, Status};
+use crate::orchestrator::PlanetType;
+
+const BASIC_RESOURCES: [BasicResourceType; 4] = [Oxygen, Hydrogen, Carbon, Silicon];
+const COMPLEX_RESOURCES: [ComplexResourceType; 6] = [Diamond, Water, Life, Robot, Glucose, AIPartner];
+const STATUSES: [Status; 3] = [Status::Running, Status::Paused, Status::Dead];
+const PLANET_TYPES: [PlanetType; 7] = [
+    PlanetType::PanicOutOfOxygen,
+    PlanetType::RustEze,
+    PlanetType::HoustonWeHaveABorrow,
+    PlanetType::Carbonium,
+    PlanetType::OneMillionCrabs,
+    PlanetType::Rustrelli,
+    PlanetType::TheCompilerStrikesBack
+];
+
+/// Serializable copy of an [`OrchestratorEvent`].
+///
+/// Resources are stored by name, since
+/// `common_game` types can't be serialized.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub(crate) enum RecordedEvent {
+    PlanetDestroyed { planet_id: u32 },
+    SunrayReceived { planet_id: u32 },
+    SunraySent { planet_id: u32 },
+    AsteroidSent { planet_id: u32 },
+    ExplorerMoved { explorer_id: u32, destination: u32 },
+    BasicResourceGenerated { explorer_id: u32, resource: String },
+    ComplexResourceGenerated { explorer_id: u32, resource: String }
+}
+
+impl From<&OrchestratorEvent> for RecordedEvent {
+    fn from(event: &OrchestratorEvent) -> Self {
+        match *event {
+            OrchestratorEvent::PlanetDestroyed { planet_id } => Self::PlanetDestroyed { planet_id },
+            OrchestratorEvent::SunrayReceived { planet_id } => Self::SunrayReceived { planet_id },
+            OrchestratorEvent::SunraySent { planet_id } => Self::SunraySent { planet_id },
+            OrchestratorEvent::AsteroidSent { planet_id } => Self::AsteroidSent { planet_id },
+            OrchestratorEvent::ExplorerMoved { explorer_id, destination } =>
+                Self::ExplorerMoved { explorer_id, destination },
+            OrchestratorEvent::BasicResourceGenerated { explorer_id, resource } =>
+                Self::BasicResourceGenerated { explorer_id, resource: format!("{:?}", resource) },
+            OrchestratorEvent::ComplexResourceGenerated { explorer_id, resource } =>
+                Self::ComplexResourceGenerated { explorer_id, resource: format!("{:?}", resource) }
+        }
+    }
+}
+
+impl RecordedEvent {
+    /// Rebuilds the original event, failing if the resource name is unknown
+    pub(crate) fn to_event(&self) -> io::Result<OrchestratorEvent> {
+        let event = match *self {
+            Self::PlanetDestroyed { planet_id } => OrchestratorEvent::PlanetDestroyed { planet_id },
+            Self::SunrayReceived { planet_id } => OrchestratorEvent::SunrayReceived { planet_id },
+            Self::SunraySent { planet_id } => OrchestratorEvent::SunraySent { planet_id },
+            Self::AsteroidSent { planet_id } => OrchestratorEvent::AsteroidSent { planet_id },
+            Self::ExplorerMoved { explorer_id, destination } =>
+                OrchestratorEvent::ExplorerMoved { explorer_id, destination },
+            Self::BasicResourceGenerated { explorer_id, ref resource } => {
+                let resource = by_name(&BASIC_RESOURCES, "basic resource", resource)?;
+                OrchestratorEvent::BasicResourceGenerated { explorer_id, resource }
+            }
+            Self::ComplexResourceGenerated { explorer_id, ref resource } => {
+                let resource = by_name(&COMPLEX_RESOURCES, "complex resource", resource)?;
+                OrchestratorEvent::ComplexResourceGenerated { explorer_id, resource }
+            }
+        };
+
+        Ok(event)
+    }
+}
+
+/// Serializable copy of a [`PlanetInfo`],
+/// the planet type and status are stored by name
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+struct RecordedPlanet {
+    id: u32,
+    name: String,
+    status: String,
+    energy_cells: Vec<bool>,
+    charged_cells_count: usize,
+    rocket: bool
+}
+
+impl RecordedPlanet {
+    fn new(id: u32, info: &PlanetInfo) -> Self {
+        Self {
+            id,
+            name: format!("{:?}", info.name),
+            status: format!("{:?}", info.status),
+            energy_cells: info.energy_cells.clone(),
+            charged_cells_count: info.charged_cells_count,
+            rocket: info.rocket
+        }
+    }
+
+    /// Rebuilds the original info, failing if a name is unknown
+    fn to_info(&self) -> io::Result<PlanetInfo> {
+        Ok(PlanetInfo {
+            name: by_name(&PLANET_TYPES, "planet type", &self.name)?,
+            status: by_name(&STATUSES, "status", &self.status)?,
+            energy_cells: self.energy_cells.clone(),
+            charged_cells_count: self.charged_cells_count,
+            rocket: self.rocket
+        })
+    }
+}
+
+/// Serializable copy of an [`ExplorerInfo`].
+///
+/// The bag holds `common_game` resources, it is only written
+/// as text for whoever reads the file: a replayed explorer has an empty bag.
+#[derive(Serialize, Deserialize)]
+struct RecordedExplorer {
+    id: u32,
+    status: String,
+    current_planet_id: u32,
+    bag: String
+}
+
+impl RecordedExplorer {
+    fn new(id: u32, info: &ExplorerInfo) -> Self {
+        Self {
+            id,
+            status: format!("{:?}", info.status),
+            current_planet_id: info.current_planet_id,
+            bag: format!("{:?}", info.bag)
+        }
+    }
+
+    /// Rebuilds the original info but its bag, failing if the status is unknown
+    fn to_info(&self) -> io::Result<ExplorerInfo> {
+        Ok(ExplorerInfo {
+            status: by_name(&STATUSES, "status", &self.status)?,
+            current_planet_id: self.current_planet_id,
+            bag: default()
+        })
+    }
+}
+
+/// Value of `values` written as `name`, recordings store them by their `Debug` name
+fn by_name<T: Copy + fmt::Debug>(values: &[T], kind: &str, name: &str) -> io::Result<T> {
+    values.iter().find(|value| format!("{:?}", value) == name).copied().ok_or_else(|| {
+        let message = format!("unknown {kind} {name}, the recording comes from another version of the game");
+        io::Error::new(io::ErrorKind::InvalidData, message)
+    })
+}
+
+fn record_planets(planets: &PlanetInfoMap) -> Vec<RecordedPlanet> {
+    let mut recorded: Vec<RecordedPlanet> = planets.iter().map(|(&id, info)| RecordedPlanet::new(id, info)).collect();
+    recorded.sort_unstable_by_key(|p| p.id);
+    recorded
+}
+
+fn record_explorers(explorers: &ExplorerInfoMap) -> Vec<RecordedExplorer> {
+    explorer_ids().filter_map(|id| Some(RecordedExplorer::new(id, explorers.get(&id)?))).collect()
+}
+
+fn restore_maps(
+    planets: &[RecordedPlanet],
+    explorers: &[RecordedExplorer]
+) -> io::Result<(PlanetInfoMap, ExplorerInfoMap)> {
+    let mut planet_map = PlanetInfoMap::default();
+    for planet in planets {
+        planet_map.insert(planet.id, planet.to_info()?);
+    }
+
+    let mut explorer_map = ExplorerInfoMap::default();
+    for explorer in explorers {
+        explorer_map.insert(explorer.id, explorer.to_info()?);
+    }
+
+    Ok((planet_map, explorer_map))
+}
+
+/// First line of a recording: the galaxy as it was before the first tick
+#[derive(Serialize, Deserialize)]
+struct RecordingHeader {
+    seed: u64,
+    snapshot: GalaxySnapshot,
+    planets: Vec<RecordedPlanet>,
+    explorers: Vec<RecordedExplorer>
+}
+
+/// Every other line of a recording, a [`RecordedTick`] made serializable
+#[derive(Serialize, Deserialize)]
+struct TickLine {
+    tick: u32,
+    events: Vec<RecordedEvent>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    edges: Option<Vec<(u32, u32)>>,
+    planets: Vec<RecordedPlanet>,
+    explorers: Vec<RecordedExplorer>
+}
+
+impl TickLine {
+    /// Fails on the names this version of the game doesn't know,
+    /// so a replay never silently drops an event
+    fn into_frame(self) -> io::Result<RecordedTick> {
+        for event in &self.events {
+            event.to_event()?;
+        }
+
+        let (planets, explorers) = restore_maps(&self.planets, &self.explorers)?;
+        Ok(RecordedTick { tick: self.tick, events: self.events, edges: self.edges, planets, explorers })
+    }
+}
+
+/// Frame of the [`GameHistory`], also written to the recording as a [`TickLine`]
+#[derive(Clone)]
+pub(crate) struct RecordedTick {
+    pub tick: u32,
+    pub events: Vec<RecordedEvent>,
+    /// Links of the galaxy, only when they changed during this tick
+    pub edges: Option<Vec<(u32, u32)>>,
+    /// State of the galaxy once the events were handled
+    pub planets: PlanetInfoMap,
+    pub explorers: ExplorerInfoMap
+}
+
+/// Writes the events of every tick to a JSON Lines file
+#[derive(Resource)]
+pub(crate) struct Recorder {
+    writer: BufWriter<File>,
+    /// Games of the session recorded so far, this one excluded
+    game: u32
+}
+
+impl Recorder {
+    pub(crate) fn record_tick(&mut self, frame: &RecordedTick) -> io::Result<()> {
+        self.write_line(&TickLine {
+            tick: frame.tick,
+            events: frame.events.clone(),
+            edges: frame.edges.clone(),
+            planets: record_planets(&frame.planets),
+            explorers: record_explorers(&frame.explorers)
+        })
+    }
+
+    fn write_line(&mut self, line: &impl Serialize) -> io::Result<()> {
+        serde_json::to_writer(&mut self.writer, line)?;
+        self.writer.write_all(b"\n")?;
+        // flush every line, so a crash still leaves a usable recording
+        self.writer.flush()
+    }
+}
+
+/// Opens the recording file and writes the starting galaxy to it,
+/// a restarted game goes to a new file, see [`recording_path`]
+pub(crate) fn start_recording(
+    mut commands: Commands,
+    settings: Res<GuiSettings>,
+    snapshot: Res<GalaxySnapshot>,
+    planets: Res<PlanetInfoRes>,
+    explorers: Res<ExplorerInfoRes>,
+    previous: Option<Res<Recorder>>
+) {
+    let Some(path) = &settings.record_path else {
+        return;
+    };
+
+    let game = previous.map_or(0, |recorder| recorder.game + 1);
+    let path = &recording_path(path, game);
+
+    let header = RecordingHeader {
+        seed: settings.seed,
+        snapshot: snapshot.clone(),
+        planets: record_planets(&planets.map),
+        explorers: record_explorers(&explorers.map)
+    };
+
+    let recorder = File::create(path)
+        .map(|file| Recorder { writer: BufWriter::new(file), game })
+        .and_then(|mut recorder| recorder.write_line(&header).map(|_| recorder));
+
+    match recorder {
+        Ok(recorder) => {
+            info!("recording game to {}", path.display());
+            commands.insert_resource(recorder);
+        }
+        Err(e) => log::error!("Failed to start recording to {}: {e}", path.display())
+    }
+}
+
+/// File the `game`-th game of the session is recorded to: the first one
+/// goes to `path`, the restarted ones get a `-<game>` suffix
+fn recording_path(path: &Path, game: u32) -> PathBuf {
+    if game == 0 {
+        return path.to_path_buf();
+    }
+
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{stem}-{game}.{}", extension.to_string_lossy()),
+        None => format!("{stem}-{game}")
+    };
+    path.with_file_name(name)
+}
+
+/// Reads the header and every frame of a recording,
+/// the first frame being the galaxy described by the header
+fn read_recording(path: &Path) -> io::Result<(RecordingHeader, Vec<RecordedTick>)> {
+    let mut lines = BufReader::new(File::open(path)?).lines();
+
+    let header = lines.next().ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "empty recording"))??;
+    let header: RecordingHeader = serde_json::from_str(&header)?;
+
+    let (planets, explorers) = restore_maps(&header.planets, &header.explorers)?;
+    let edges = Some(header.snapshot.edges.clone());
+    let mut frames = vec![RecordedTick { tick: 0, events: Vec::new(), edges, planets, explorers }];
+
+    for line in lines {
+        let line: TickLine = serde_json::from_str(&line?)?;
+        frames.push(line.into_frame()?);
+    }
+
+    Ok((header, frames))
+}
+
+/// Replaces [`setup_orchestrator`](super::game::setup_orchestrator)
+/// when playing back a recording
+pub(crate) fn setup_replay(mut commands: Commands, settings: Res<GuiSettings>) {
+    let path = settings.replay_path.as_ref().expect("replay mode without a recording");
+
+    // the whole game is known in advance, the timeline can reach any tick
+    let (header, frames) = read_recording(path).unwrap_or_else(|e| {
+        log::error!("Failed to load recording {}: {e}", path.display());
+        panic!("Failed to load recording {}: {e}", path.display());
+    });
+
+    let first_string = format!("Replaying {} (seed {}).\nWelcome back!", path.display(), header.seed);
+
+    // the recorded seed wins, so the replay looks like the original game
+    let settings = GuiSettings { seed: header.seed, ..settings.clone() };
+
+    insert_game_resources(&mut commands, &settings, header.snapshot, frames, first_string);
+
+    commands.insert_resource(settings);
+}
+
+/// Same as [`game_loop`](super::game::game_loop),
+/// but the events come from the recorded history
+pub(crate) fn replay_loop(
+    mut commands: Commands,
+    mut history: ResMut<GameHistory>,
+    mut planets: ResMut<PlanetInfoRes>,
+    mut explorers: ResMut<ExplorerInfoRes>,
+    mut snapshot: ResMut<GalaxySnapshot>,
+    mut timer: ResMut<GameTimer>,
+    mut ticks: ResMut<TickCounter>,
+    mut step: ResMut<StepRequest>,
+    mut log: ResMut<EventLog>,
+    mut stats: ResMut<GameStats>,
+    state: Res<GameState>,
+    time: Res<Time>
+) {
+    let tick_now = match *state {
+        GameState::Playing => {
+            timer.tick(time.delta());
+            timer.is_finished()
+        }
+        GameState::Paused | GameState::WaitingStart => std::mem::take(&mut step.0),
+        _ => false
+    };
+
+    if !tick_now {
+        return;
+    }
+    timer.reset();
+
+    if history.current == history.latest() {
+        log.push(LogEntry::new(**ticks, Severity::Info, LogKind::Game, "replay finished"));
+        commands.insert_resource(GameState::Paused);
+        return;
+    }
+
+    history.current += 1;
+    let frame = &history.frames[history.current];
+
+    // every event was checked when the recording was read
+    let events: Vec<OrchestratorEvent> = frame.events.iter().filter_map(|event| event.to_event().ok()).collect();
+
+    // frames played again after a jump back are only counted once
+    let sample = stats.at(frame.tick);
+    (sample.sunrays, sample.asteroids) = (0, 0);
+    sample.resources.clear();
+
+    handle_tick(&mut commands, &events, frame.tick, &mut log, &mut stats);
+
+    planets.map = frame.planets.clone();
+    explorers.map = frame.explorers.clone();
+    **ticks = frame.tick;
+
+    if let Some(edges) = &frame.edges {
+        snapshot.edges.clone_from(edges);
+    }
+
+    finish_if_over(&mut commands, &planets, &explorers, **ticks, &mut log);
+}
+
+/// Replays can only be started, paused and restarted,
+/// manual actions need a live orchestrator
+pub(crate) fn replay_menu_action(
+    mut commands: Commands,
+    action_query: Query<(&Interaction, &ButtonActions), (Changed<Interaction>, With<Button>)>,
+    mut state: ResMut<GameState>
+) {
+    for (&interaction, action) in &action_query {
+        // a finished replay can only be restarted
+        if *state == GameState::Finished && !matches!(action, ButtonActions::Restart) {
+            continue;
+        }
+
+        if interaction == Interaction::Pressed {
+            match action {
+                ButtonActions::StartGame =>
+                    if *state == GameState::WaitingStart {
+                        *state = GameState::Playing;
+                        info!("replay started");
+                    },
+                ButtonActions::Resume =>
+                    if matches!(*state, GameState::Paused | GameState::Reviewing) {
+                        *state = GameState::Playing;
+                        info!("replay resumed");
+                    },
+                ButtonActions::StopGame =>
+                    if state.set_if_neq(GameState::Paused) {
+                        info!("replay paused");
+                    },
+                ButtonActions::Restart => {
+                    info!("restarting the replay");
+                    commands.trigger(JumpToFrame { frame: 0 });
+                    // applied after the jump, which would leave the replay in review
+                    commands.insert_resource(GameState::WaitingStart);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<T: Serialize + for<'de> Deserialize<'de>>(value: &T) -> T {
+        serde_json::from_str(&serde_json::to_string(value).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn every_event_survives_a_recording() {
+        let mut events = vec![
+            OrchestratorEvent::PlanetDestroyed { planet_id: 1 },
+            OrchestratorEvent::SunrayReceived { planet_id: 2 },
+            OrchestratorEvent::SunraySent { planet_id: 3 },
+            OrchestratorEvent::AsteroidSent { planet_id: 4 },
+            OrchestratorEvent::ExplorerMoved { explorer_id: 8, destination: 5 }
+        ];
+        events.extend(
+            BASIC_RESOURCES.map(|resource| OrchestratorEvent::BasicResourceGenerated { explorer_id: 8, resource })
+        );
+        events.extend(
+            COMPLEX_RESOURCES.map(|resource| OrchestratorEvent::ComplexResourceGenerated { explorer_id: 9, resource })
+        );
+
+        for event in &events {
+            let recorded = RecordedEvent::from(event);
+            let parsed: RecordedEvent = round_trip(&recorded);
+            assert_eq!(RecordedEvent::from(&parsed.to_event().unwrap()), recorded);
+        }
+    }
+
+    #[test]
+    fn planets_survive_a_recording() {
+        let mut planets = PlanetInfoMap::default();
+        for (id, (name, status)) in PLANET_TYPES.into_iter().zip(STATUSES.into_iter().cycle()).enumerate() {
+            let info = PlanetInfo {
+                name,
+                status,
+                energy_cells: vec![true, false, id % 2 == 0],
+                charged_cells_count: 1 + id % 2,
+                rocket: id % 3 == 0
+            };
+            planets.insert(id as u32 + 1, info);
+        }
+
+        let recorded = record_planets(&planets);
+        let parsed: Vec<RecordedPlanet> = round_trip(&recorded);
+        let (restored, _) = restore_maps(&parsed, &[]).unwrap();
+
+        assert_eq!(record_planets(&restored), recorded);
+    }
+
+    #[test]
+    fn unknown_names_are_errors() {
+        let event = RecordedEvent::BasicResourceGenerated { explorer_id: 8, resource: "Mithril".to_string() };
+        assert!(event.to_event().is_err());
+
+        let planet = RecordedPlanet {
+            id: 1,
+            name: "Pluto".to_string(),
+            status: "Running".to_string(),
+            energy_cells: Vec::new(),
+            charged_cells_count: 0,
+            rocket: false
+        };
+        assert!(restore_maps(&[planet], &[]).is_err());
+    }
+
+    #[test]
+    fn restarted_games_are_recorded_to_new_files() {
+        let path = Path::new("records/game.jsonl");
+
+        assert_eq!(recording_path(path, 0), path);
+        assert_eq!(recording_path(path, 2), Path::new("records/game-2.jsonl"));
+        assert_eq!(recording_path(Path::new("game"), 1), Path::new("game-1"));
+    }
+}
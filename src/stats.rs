@@ -0,0 +1,211 @@
+use bevy::prelude::*;
+
+use super::ecs::components::{ButtonActions, StatsChartBars, StatsChartLabel, StatsPanel};
+use super::ecs::resources::{GameStats, PlanetInfoRes, TickCounter, TickStats};
+use super::galaxy::{explorer_ids, explorer_tint};
+use super::types::Status;
+use super::utils::constants::{ASTEROID_BAR_COLOR, CENTER_PANEL_MARGIN, STATS_WINDOW, SUNRAY_BAR_COLOR};
+
+/// One of the charts of the dashboard
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum StatsChart {
+    AlivePlanets,
+    ChargedCells,
+    /// Sunrays and asteroids sent, side by side
+    Celestials,
+    /// Resources generated by each explorer, stacked
+    Resources
+}
+
+impl StatsChart {
+    const ALL: [StatsChart; 4] =
+        [StatsChart::AlivePlanets, StatsChart::ChargedCells, StatsChart::Celestials, StatsChart::Resources];
+
+    fn title(self, latest: Option<&TickStats>) -> String {
+        let Some(s) = latest else {
+            return self.name().to_string();
+        };
+
+        match self {
+            StatsChart::AlivePlanets => format!("{}: {}", self.name(), s.alive_planets),
+            StatsChart::ChargedCells => format!("{}: {}", self.name(), s.charged_cells),
+            StatsChart::Celestials => format!("{}: {} / {}", self.name(), s.sunrays, s.asteroids),
+            StatsChart::Resources => format!("{}: {}", self.name(), s.resources.values().sum::<u32>())
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            StatsChart::AlivePlanets => "Alive planets",
+            StatsChart::ChargedCells => "Charged cells",
+            StatsChart::Celestials => "Sunrays / asteroids",
+            StatsChart::Resources => "Resources per explorer"
+        }
+    }
+}
+
+/// Draws the (initially hidden) statistics dashboard
+/// on top of the galaxy view, above the timeline
+pub(crate) fn draw_stats_panel(mut commands: Commands) {
+    let panel = (
+        BackgroundColor(Color::Srgba(Srgba { red: 0.12, green: 0.18, blue: 0.24, alpha: 0.9 })),
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(CENTER_PANEL_MARGIN),
+            right: Val::Px(CENTER_PANEL_MARGIN),
+            bottom: Val::Px(60.),
+            height: Val::Percent(45.),
+            flex_direction: FlexDirection::Column,
+            padding: UiRect::all(Val::Px(10.)),
+            row_gap: Val::Px(4.),
+            ..default()
+        },
+        Visibility::Hidden,
+        StatsPanel
+    );
+
+    commands.spawn(panel).with_children(|parent| {
+        parent.spawn(Text::new(format!("Statistics (last {} ticks)", STATS_WINDOW)));
+
+        for chart in StatsChart::ALL {
+            parent.spawn((Text::new(chart.name()), TextFont { font_size: 12., ..default() }, StatsChartLabel(chart)));
+            parent.spawn((
+                BackgroundColor(Color::Srgba(Srgba { red: 0., green: 0., blue: 0., alpha: 0.6 })),
+                Node {
+                    flex_direction: FlexDirection::Row,
+                    align_items: AlignItems::FlexEnd,
+                    flex_grow: 1.,
+                    ..default()
+                },
+                StatsChartBars(chart)
+            ));
+        }
+    });
+}
+
+pub(crate) fn stats_panel_action(
+    action_query: Query<(&Interaction, &ButtonActions), (Changed<Interaction>, With<Button>)>,
+    mut panel: Single<&mut Visibility, With<StatsPanel>>
+) {
+    for (&interaction, action) in &action_query {
+        if interaction == Interaction::Pressed
+            && let ButtonActions::ShowStats = action
+        {
+            panel.toggle_visible_hidden();
+        }
+    }
+}
+
+/// Samples the planets of the current tick, the events
+/// are counted by [`handle_tick`](super::game::handle_tick)
+pub(crate) fn record_planet_stats(
+    planets: Res<PlanetInfoRes>,
+    ticks: Res<TickCounter>,
+    mut stats: ResMut<GameStats>
+) {
+    if !planets.is_changed() {
+        return;
+    }
+
+    let (mut alive, mut charged) = (0, 0);
+    for (_, info) in planets.map.iter() {
+        if info.status != Status::Dead {
+            alive += 1;
+            charged += info.charged_cells_count;
+        }
+    }
+
+    let sample = stats.at(**ticks);
+    sample.alive_planets = alive;
+    sample.charged_cells = charged;
+}
+
+/// Redraws the bars of every chart, only while the dashboard is shown
+pub(crate) fn update_stats_panel(
+    mut commands: Commands,
+    stats: Res<GameStats>,
+    panel: Single<Ref<Visibility>, With<StatsPanel>>,
+    bars: Query<(Entity, &StatsChartBars)>,
+    mut titles: Query<(&mut Text, &StatsChartLabel)>
+) {
+    if **panel == Visibility::Hidden || (!stats.is_changed() && !panel.is_changed()) {
+        return;
+    }
+
+    let window = &stats.samples[stats.samples.len().saturating_sub(STATS_WINDOW)..];
+
+    for (mut text, &StatsChartLabel(chart)) in &mut titles {
+        **text = chart.title(window.last());
+    }
+
+    let width = Val::Percent(100. / STATS_WINDOW as f32);
+
+    for (entity, &StatsChartBars(chart)) in &bars {
+        commands.entity(entity).despawn_children();
+
+        // every bar is relative to the highest one in the window
+        let value = |s: &TickStats| -> f32 {
+            match chart {
+                StatsChart::AlivePlanets => s.alive_planets as f32,
+                StatsChart::ChargedCells => s.charged_cells as f32,
+                StatsChart::Celestials => s.sunrays.max(s.asteroids) as f32,
+                StatsChart::Resources => s.resources.values().sum::<u32>() as f32
+            }
+        };
+        let max = window.iter().map(value).fold(1., f32::max);
+        let height = |v: f32| Val::Percent(100. * v / max);
+
+        // stacked bars grow upwards, the others stand next to each other
+        let (direction, align) = match chart {
+            StatsChart::Resources => (FlexDirection::ColumnReverse, AlignItems::Stretch),
+            _ => (FlexDirection::Row, AlignItems::FlexEnd)
+        };
+
+        commands.entity(entity).with_children(|parent| {
+            for sample in window {
+                let mut column = parent.spawn(Node {
+                    width,
+                    height: Val::Percent(100.),
+                    flex_direction: direction,
+                    align_items: align,
+                    padding: UiRect::right(Val::Px(1.)),
+                    ..default()
+                });
+
+                match chart {
+                    StatsChart::AlivePlanets | StatsChart::ChargedCells => {
+                        column.with_child((
+                            Node { flex_grow: 1., height: height(value(sample)), ..default() },
+                            BackgroundColor(Color::srgb(0.35, 0.75, 0.35))
+                        ));
+                    }
+                    StatsChart::Celestials => {
+                        let bars = [(sample.sunrays, SUNRAY_BAR_COLOR), (sample.asteroids, ASTEROID_BAR_COLOR)];
+                        column.with_children(|column| {
+                            for (count, color) in bars {
+                                column.spawn((
+                                    Node { flex_grow: 1., height: height(count as f32), ..default() },
+                                    BackgroundColor(color)
+                                ));
+                            }
+                        });
+                    }
+                    // one colour per explorer, as in the galaxy
+                    StatsChart::Resources => {
+                        column.with_children(|column| {
+                            let mut explorers: Vec<(&u32, &u32)> = sample.resources.iter().collect();
+                            explorers.sort_unstable();
+                            for (&id, &count) in explorers {
+                                let index = explorer_ids().position(|other| other == id).unwrap_or_default();
+                                column.spawn((
+                                    Node { height: height(count as f32), ..default() },
+                                    BackgroundColor(explorer_tint(index))
+                                ));
+                            }
+                        });
+                    }
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,105 @@
+use bevy::prelude::*;
+
+use super::ecs::components::{Edge, Explorer, Planet, StatusIcon};
+use super::ecs::resources::{ExplorerInfoRes, PlanetInfoRes};
+use super::types::Status;
+use super::utils::constants::{INACTIVE_EDGE_COLOR, PAUSED_TINT, STATUS_ICON_COLOR, STATUS_ICON_SIZE};
+
+/// Icon next to a planet or explorer, only shown while it isn't running
+pub(crate) fn spawn_status_icon(parent: &mut ChildSpawnerCommands, offset: Vec2) {
+    parent.spawn((
+        StatusIcon,
+        Text2d::default(),
+        TextFont { font_size: STATUS_ICON_SIZE, ..default() },
+        TextColor(STATUS_ICON_COLOR),
+        Transform::from_xyz(offset.x, offset.y, 0.3),
+        Visibility::Hidden
+    ));
+}
+
+fn set_icon(
+    icons: &mut Query<(&mut Text2d, &mut Visibility), With<StatusIcon>>,
+    children: &Children,
+    status: &Status
+) {
+    let symbol = match status {
+        Status::Running => None,
+        Status::Paused => Some("II"),
+        Status::Dead => Some("X")
+    };
+
+    for &child in children {
+        let Ok((mut text, mut visibility)) = icons.get_mut(child) else {
+            continue;
+        };
+
+        match symbol {
+            Some(symbol) => {
+                text.0 = symbol.to_string();
+                visibility.set_if_neq(Visibility::Inherited);
+            }
+            None => {
+                visibility.set_if_neq(Visibility::Hidden);
+            }
+        }
+    }
+}
+
+/// Greys out the planets that aren't running and shows their icon,
+/// for new sprites too since they are spawned as running
+pub(crate) fn show_planet_status(
+    planet_info: Res<PlanetInfoRes>,
+    new_planets: Query<(), Added<Planet>>,
+    mut planets: Query<(&Planet, &mut Sprite, &Children)>,
+    mut icons: Query<(&mut Text2d, &mut Visibility), With<StatusIcon>>
+) {
+    if !planet_info.is_changed() && new_planets.is_empty() {
+        return;
+    }
+
+    for (planet, mut sprite, children) in &mut planets {
+        let status = planet_info.map.get_status(&planet.id);
+        sprite.color = if status == Status::Running { Color::WHITE } else { PAUSED_TINT };
+        set_icon(&mut icons, children, &status);
+    }
+}
+
+/// Same as [`show_planet_status`], the explorer keeps a hint of its tint
+pub(crate) fn show_explorer_status(
+    explorer_info: Res<ExplorerInfoRes>,
+    new_explorers: Query<(), Added<Explorer>>,
+    mut explorers: Query<(&Explorer, &mut Sprite, &Children)>,
+    mut icons: Query<(&mut Text2d, &mut Visibility), With<StatusIcon>>
+) {
+    if !explorer_info.is_changed() && new_explorers.is_empty() {
+        return;
+    }
+
+    for (explorer, mut sprite, children) in &mut explorers {
+        let Some(info) = explorer_info.map.get(&explorer.id) else {
+            continue;
+        };
+
+        sprite.color =
+            if info.status == Status::Running { explorer.tint } else { explorer.tint.mix(&PAUSED_TINT, 0.7) };
+        set_icon(&mut icons, children, &info.status);
+    }
+}
+
+/// Tints the links touching a planet that isn't running.
+/// Dashed links already lead to a dead planet, so they are left alone.
+pub(crate) fn show_edge_status(
+    planet_info: Res<PlanetInfoRes>,
+    new_edges: Query<(), Added<Edge>>,
+    mut edges: Query<(&Edge, &mut Sprite)>
+) {
+    if !planet_info.is_changed() && new_edges.is_empty() {
+        return;
+    }
+
+    for (edge, mut sprite) in &mut edges {
+        let (a, b) = edge.connects;
+        let running = [a, b].iter().all(|id| planet_info.map.get_status(id) == Status::Running);
+        sprite.color = if running { Color::WHITE } else { INACTIVE_EDGE_COLOR };
+    }
+}
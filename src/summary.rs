@@ -0,0 +1,146 @@
+use bevy::prelude::*;
+
+use super::ecs::components::{ButtonActions, SummaryOverlay, SummaryText};
+use super::ecs::resources::{ExplorerInfoRes, GameState, GameStats, PlanetInfoRes, TickCounter};
+use super::galaxy::explorer_ids;
+use super::game::game_over;
+use super::types::Status;
+use super::utils::constants::CENTER_PANEL_MARGIN;
+
+/// What a planet died of, as far as the events tell
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DeathCause {
+    /// The last celestial sent to the planet was an asteroid
+    Asteroid,
+    Unknown
+}
+
+impl DeathCause {
+    fn describe(self) -> &'static str {
+        match self {
+            DeathCause::Asteroid => "hit by an asteroid",
+            DeathCause::Unknown => "unknown cause"
+        }
+    }
+}
+
+/// Draws the (initially hidden) overlay shown when the game is over
+pub(crate) fn draw_summary_overlay(mut commands: Commands) {
+    let overlay = (
+        BackgroundColor(Color::Srgba(Srgba { red: 0.12, green: 0.18, blue: 0.24, alpha: 0.95 })),
+        Node {
+            position_type: PositionType::Absolute,
+            left: Val::Px(CENTER_PANEL_MARGIN),
+            right: Val::Px(CENTER_PANEL_MARGIN),
+            top: Val::Percent(15.),
+            max_height: Val::Percent(70.),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::Center,
+            padding: UiRect::all(Val::Px(16.)),
+            row_gap: Val::Px(10.),
+            overflow: Overflow::scroll_y(),
+            ..default()
+        },
+        BorderRadius::all(Val::Px(15.)),
+        // above the other panels
+        GlobalZIndex(1),
+        Visibility::Hidden,
+        SummaryOverlay
+    );
+
+    commands.spawn(overlay).with_children(|parent| {
+        parent.spawn((Text::new("Game over"), TextFont { font_size: 24., ..default() }));
+        parent.spawn((Text::new(""), TextFont { font_size: 14., ..default() }, SummaryText));
+        parent.spawn((
+            Button,
+            BackgroundColor(Color::srgb(0.07, 0.30, 0.53)),
+            Node {
+                height: Val::Px(32.),
+                padding: UiRect::horizontal(Val::Px(16.)),
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BorderRadius::all(Val::Px(8.)),
+            ButtonActions::CloseSummary,
+            children![(Text::new("Close"), TextFont { font_size: 12., ..default() })]
+        ));
+    });
+}
+
+pub(crate) fn summary_action(
+    action_query: Query<(&Interaction, &ButtonActions), (Changed<Interaction>, With<Button>)>,
+    mut overlay: Single<&mut Visibility, With<SummaryOverlay>>
+) {
+    for (&interaction, action) in &action_query {
+        if interaction == Interaction::Pressed
+            && let ButtonActions::CloseSummary = action
+        {
+            **overlay = Visibility::Hidden;
+        }
+    }
+}
+
+/// Fills in and shows the overlay when the game is over,
+/// hides it as soon as the game leaves that state
+pub(crate) fn update_summary(
+    state: Res<GameState>,
+    ticks: Res<TickCounter>,
+    stats: Res<GameStats>,
+    planets: Res<PlanetInfoRes>,
+    explorers: Res<ExplorerInfoRes>,
+    mut overlay: Single<&mut Visibility, With<SummaryOverlay>>,
+    mut text: Single<&mut Text, With<SummaryText>>
+) {
+    if !state.is_changed() {
+        return;
+    }
+
+    if *state != GameState::Finished {
+        **overlay = Visibility::Hidden;
+        return;
+    }
+
+    let mut lines = vec![format!(
+        "The game lasted {} ticks, {}.",
+        **ticks,
+        game_over(&planets, &explorers).unwrap_or("it was stopped")
+    )];
+
+    let mut alive: Vec<u32> =
+        planets.map.iter().filter(|(_, info)| info.status != Status::Dead).map(|(&id, _)| id).collect();
+    alive.sort_unstable();
+    lines.push(format!("Surviving planets: {}", id_list(&alive)));
+
+    let survivors: Vec<u32> = explorer_ids()
+        .filter(|id| {
+            explorers.map.get(id).is_some_and(|info| {
+                info.status != Status::Dead && planets.map.get_status(&info.current_planet_id) != Status::Dead
+            })
+        })
+        .collect();
+    lines.push(format!("Surviving explorers: {}", id_list(&survivors)));
+
+    lines.push("\nResources collected:".to_string());
+    for id in explorer_ids() {
+        let count: u32 = stats.samples.iter().filter_map(|s| s.resources.get(&id)).sum();
+        lines.push(format!("  explorer {}: {}", id, count));
+    }
+
+    lines.push("\nPlanet deaths:".to_string());
+    if stats.deaths.is_empty() {
+        lines.push("  none".to_string());
+    }
+    for (id, death) in &stats.deaths {
+        lines.push(format!("  planet {} at tick {}: {}", id, death.tick, death.cause.describe()));
+    }
+
+    text.0 = lines.join("\n");
+    **overlay = Visibility::Visible;
+}
+
+fn id_list(ids: &[u32]) -> String {
+    if ids.is_empty() {
+        return "none".to_string();
+    }
+    ids.iter().map(u32::to_string).collect::<Vec<_>>().join(", ")
+}
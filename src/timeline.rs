@@ -0,0 +1,195 @@
+use bevy::prelude::*;
+use bevy::ui::RelativeCursorPosition;
+
+use super::ecs::components::{ButtonActions, GalaxyEntities, TimelineFill, TimelineLabel, TimelineTrack};
+use super::ecs::events::JumpToFrame;
+use super::ecs::resources::{ExplorerInfoRes, GalaxySnapshot, GameHistory, GameState, GuiSettings, PlanetInfoRes,
+                            TickCounter};
+use super::game::game_over;
+use super::galaxy::spawn_galaxy;
+use super::layout::GalaxyLayout;
+use super::utils::assets::{ExplorerAssets, PlanetAssets};
+use super::utils::constants::CENTER_PANEL_MARGIN;
+
+/// Draws the timeline bar at the bottom of the galaxy view,
+/// between the two side menus
+pub(crate) fn draw_timeline(mut commands: Commands) {
+    let root = (BackgroundColor(Color::Srgba(Srgba { red: 0., green: 0., blue: 0., alpha: 0.6 })), Node {
+        position_type: PositionType::Absolute,
+        left: Val::Px(CENTER_PANEL_MARGIN),
+        right: Val::Px(CENTER_PANEL_MARGIN),
+        bottom: Val::Px(10.),
+        height: Val::Px(40.),
+        flex_direction: FlexDirection::Row,
+        align_items: AlignItems::Center,
+        column_gap: Val::Px(8.),
+        padding: UiRect::horizontal(Val::Px(8.)),
+        ..default()
+    });
+
+    let button_factory = |text: &str| {
+        (
+            Button,
+            BackgroundColor(Color::srgb(0.07, 0.30, 0.53)),
+            Node {
+                height: Val::Px(28.),
+                padding: UiRect::horizontal(Val::Px(8.)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            BorderRadius::all(Val::Px(8.)),
+            children![(Text::new(text), TextFont { font_size: 12., ..default() })]
+        )
+    };
+
+    commands.spawn(root).with_children(|parent| {
+        parent.spawn((button_factory("<"), ButtonActions::StepBack));
+
+        // the track is a button so clicks on it report an interaction
+        parent
+            .spawn((
+                Button,
+                BackgroundColor(Color::srgb(0.25, 0.25, 0.25)),
+                Node { flex_grow: 1., height: Val::Px(12.), ..default() },
+                RelativeCursorPosition::default(),
+                TimelineTrack
+            ))
+            .with_children(|track| {
+                track.spawn((
+                    BackgroundColor(Color::srgb(0.67, 0.30, 0.53)),
+                    Node { width: Val::Percent(100.), height: Val::Percent(100.), ..default() },
+                    TimelineFill
+                ));
+            });
+
+        parent.spawn((button_factory(">"), ButtonActions::StepForward));
+        parent.spawn((button_factory("Latest"), ButtonActions::GoLatest));
+        parent.spawn((Text::new("Tick 0"), TextFont { font_size: 12., ..default() }, TimelineLabel));
+    });
+}
+
+pub(crate) fn timeline_action(
+    mut commands: Commands,
+    action_query: Query<(&Interaction, &ButtonActions), (Changed<Interaction>, With<Button>)>,
+    history: Res<GameHistory>
+) {
+    for (&interaction, action) in &action_query {
+        if interaction == Interaction::Pressed {
+            let frame = match action {
+                ButtonActions::StepBack => history.current.saturating_sub(1),
+                ButtonActions::StepForward => (history.current + 1).min(history.latest()),
+                ButtonActions::GoLatest => history.latest(),
+                _ => continue
+            };
+
+            if frame != history.current {
+                commands.trigger(JumpToFrame { frame });
+            }
+        }
+    }
+}
+
+/// Jumps to the frame under the cursor when the track is clicked
+pub(crate) fn timeline_click(
+    mut commands: Commands,
+    track: Query<(&Interaction, &RelativeCursorPosition), (Changed<Interaction>, With<TimelineTrack>)>,
+    history: Res<GameHistory>
+) {
+    for (&interaction, cursor) in &track {
+        if interaction != Interaction::Pressed {
+            continue;
+        }
+
+        // normalized coordinates go from -0.5 to 0.5, centered on the node
+        let Some(position) = cursor.normalized else {
+            continue;
+        };
+
+        let fraction = (position.x + 0.5).clamp(0., 1.);
+        let frame = (fraction * history.latest() as f32).round() as usize;
+
+        if frame != history.current {
+            commands.trigger(JumpToFrame { frame });
+        }
+    }
+}
+
+pub(crate) fn update_timeline(
+    history: Res<GameHistory>,
+    mut fill: Single<&mut Node, With<TimelineFill>>,
+    mut label: Single<&mut Text, With<TimelineLabel>>
+) {
+    if !history.is_changed() {
+        return;
+    }
+
+    let latest = history.latest();
+    let fraction = if latest == 0 { 1. } else { history.current as f32 / latest as f32 };
+
+    fill.width = Val::Percent(fraction * 100.);
+    label.0 = format!("Tick {} / {}", history.frames[history.current].tick, history.frames[latest].tick);
+}
+
+/// Despawns the whole galaxy and spawns it again
+/// as it was at the requested frame
+pub(crate) fn jump_to_frame(
+    event: On<JumpToFrame>,
+    mut commands: Commands,
+    mut history: ResMut<GameHistory>,
+    mut planets: ResMut<PlanetInfoRes>,
+    mut explorers: ResMut<ExplorerInfoRes>,
+    mut snapshot: ResMut<GalaxySnapshot>,
+    mut ticks: ResMut<TickCounter>,
+    mut state: ResMut<GameState>,
+    layout: Res<GalaxyLayout>,
+    settings: Res<GuiSettings>,
+    planet_assets: Res<PlanetAssets>,
+    explorer_assets: Res<ExplorerAssets>,
+    galaxy_entities: Query<Entity, GalaxyEntities>
+) {
+    let Some(frame) = history.frames.get(event.frame) else {
+        warn!("tried to jump to frame {} out of {}", event.frame, history.frames.len());
+        return;
+    };
+
+    info!("jumping to tick {}", frame.tick);
+
+    for entity in galaxy_entities {
+        commands.entity(entity).despawn();
+    }
+
+    planets.map = frame.planets.clone();
+    explorers.map = frame.explorers.clone();
+    **ticks = frame.tick;
+
+    // links as they were at that frame, the first one always has them
+    if let Some(edges) = history.frames[..=event.frame].iter().rev().find_map(|f| f.edges.as_ref()) {
+        snapshot.edges.clone_from(edges);
+    }
+
+    let ghosts = settings.ghost_planets;
+    spawn_galaxy(&mut commands, &layout, &planets, &explorers, &planet_assets, &explorer_assets, ghosts);
+
+    // the edges are redrawn once the planets exist
+    snapshot.set_changed();
+
+    history.current = event.frame;
+
+    if event.frame != history.latest() {
+        state.set_if_neq(GameState::Reviewing);
+    } else if **ticks > 0 && game_over(&planets, &explorers).is_some() {
+        // back to the end of a game that is over
+        state.set_if_neq(GameState::Finished);
+    } else if *state == GameState::Reviewing {
+        *state = GameState::Paused;
+    }
+}
+
+/// A live game can't be played from the past:
+/// as soon as it leaves the review, the latest frame is shown again
+pub(crate) fn leave_review(mut commands: Commands, state: Res<GameState>, history: Res<GameHistory>) {
+    if state.is_changed() && *state != GameState::Reviewing && history.current != history.latest() {
+        commands.trigger(JumpToFrame { frame: history.latest() });
+    }
+}
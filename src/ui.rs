@@ -3,16 +3,21 @@ use bevy::picking::hover::HoverMap;
 use bevy::prelude::*;
 use common_game::components::resource::BasicResourceType::Carbon;
 use common_game::components::resource::ComplexResourceType::Diamond;
+use rand::Rng;
 
 use super::ecs::components::{ButtonActions, DropdownButton, DropdownItem, DropdownLabel, DropdownList, DropdownRoot,
-                             Edge, ExplorerOnlyButton, LogText, PlanetOnlyButton, UiExplorerText, UiPlanetText};
+                             ExplorerOnlyButton, LayoutLabel, PlanetOnlyButton, SpeedLabel, UiExplorerText,
+                             UiPlanetText};
 use super::ecs::events::Scroll;
-use super::ecs::resources::{EntityClickRes, ExplorerInfoRes, GameState, OrchestratorResource, PlanetInfoRes};
+use super::ecs::resources::{EntityClickRes, ExplorerInfoRes, GalaxySnapshot, GameState, GuiSettings,
+                            OrchestratorResource, PlanetInfoRes, SessionRng, SimulationSpeed, StepRequest};
+use super::event_log::spawn_log_panel;
+use super::game::restart_game;
 use crate::gui::types;
 use crate::orchestrator::OrchestratorManualAction::{GenerateBasic, GenerateComplex, MoveExplorer, SendAsteroid,
                                                     SendSunray};
 
-pub(crate) fn draw_game_options_menu(mut commands: Commands) {
+pub(crate) fn draw_game_options_menu(mut commands: Commands, settings: Res<GuiSettings>) {
     let root = Node {
         width: Val::Px(350.),
         height: Val::Percent(100.0),
@@ -33,16 +38,17 @@ pub(crate) fn draw_game_options_menu(mut commands: Commands) {
 
     let button_row = Node { width: Val::Percent(100.0), flex_direction: FlexDirection::Row, ..default() };
 
-    let log_square = (BackgroundColor(Color::Srgba(Srgba { red: 0., green: 0., blue: 0., alpha: 0.6 })), Node {
+    let log_square = Node {
         flex_direction: FlexDirection::Column,
         align_self: AlignSelf::Stretch,
         height: Val::Percent(50.),
-        overflow: Overflow::scroll_y(),
         ..default()
-    });
+    };
 
     let title_text = Text::new("Galaxy Menu");
 
+    let seed_text = (Text::new(format!("Seed: {}", settings.seed)), TextFont { font_size: 12., ..default() });
+
     let button_factory = |text: Text| {
         (
             Button,
@@ -71,6 +77,13 @@ pub(crate) fn draw_game_options_menu(mut commands: Commands) {
         parent.spawn(side_menu_container).with_children(|parent| {
             // 3a. Menu title
             parent.spawn(title_text);
+            parent.spawn(seed_text);
+            parent.spawn((Text::new("Speed: 1x"), TextFont { font_size: 12., ..default() }, SpeedLabel));
+            parent.spawn((
+                Text::new(format!("Layout: {}", settings.layout.name())),
+                TextFont { font_size: 12., ..default() },
+                LayoutLabel
+            ));
 
             // 3b. Button Row
             parent.spawn(button_row.clone()).with_children(|parent| {
@@ -83,22 +96,50 @@ pub(crate) fn draw_game_options_menu(mut commands: Commands) {
 
             parent.spawn(button_row.clone()).with_children(|parent| {
                 //4a. button 1
-                parent.spawn((button_factory(Text::new("Restart")), ButtonActions::StartGame));
+                parent.spawn((button_factory(Text::new("Resume")), ButtonActions::Resume));
 
                 //4b. button 2
-                parent.spawn((button_factory(Text::new("Blind")), ButtonActions::Blind));
+                parent.spawn((button_factory(Text::new("Restart")), ButtonActions::Restart));
             });
 
             parent.spawn(button_row.clone()).with_children(|parent| {
                 //4a. button 1
+                parent.spawn((button_factory(Text::new("Blind")), ButtonActions::Blind));
+
+                //4b. button 2
                 parent.spawn((button_factory(Text::new("Nuke")), ButtonActions::Nuke));
+            });
+
+            parent.spawn(button_row.clone()).with_children(|parent| {
+                //4a. button 1
+                parent.spawn((button_factory(Text::new("Slower")), ButtonActions::SpeedDown));
+
+                //4b. button 2
+                parent.spawn((button_factory(Text::new("Faster")), ButtonActions::SpeedUp));
+            });
+
+            parent.spawn(button_row.clone()).with_children(|parent| {
+                //4a. button 1
+                parent.spawn((button_factory(Text::new("Step one tick")), ButtonActions::StepOnce));
+
+                //4b. button 2
+                parent.spawn((button_factory(Text::new("Explorer Messages")), ButtonActions::ShowExplorerMessages));
+            });
+
+            parent.spawn(button_row.clone()).with_children(|parent| {
+                //4a. button 1
+                parent.spawn((button_factory(Text::new("Change layout")), ButtonActions::CycleLayout));
 
                 //4b. button 2
-                parent.spawn((button_factory(Text::new("Explorer Messages")), ButtonActions::StopGame));
+                parent.spawn((button_factory(Text::new("Fit galaxy")), ButtonActions::FitGalaxy));
             });
-            parent.spawn(log_square).with_children(|parent| {
-                parent.spawn((Text::new(""), LogText));
+
+            parent.spawn(button_row.clone()).with_children(|parent| {
+                parent.spawn((button_factory(Text::new("Export log")), ButtonActions::ExportLog));
+                parent.spawn((button_factory(Text::new("Statistics")), ButtonActions::ShowStats));
             });
+
+            parent.spawn(log_square).with_children(spawn_log_panel);
         });
     });
 }
@@ -238,31 +279,39 @@ pub(crate) fn draw_entity_info_menu(mut commands: Commands) {
     });
 }
 
+/// Lists the planets linked to the one the selected explorer is visiting,
+/// refreshed whenever the topology or the explorer changes
 pub fn populate_dropdown(
     mut commands: Commands,
-    edges: Query<&Edge>,
+    snapshot: Res<GalaxySnapshot>,
     list: Single<Entity, With<DropdownList>>,
     explorer_status: Res<ExplorerInfoRes>,
+    planet_status: Res<PlanetInfoRes>,
     target_entity: Res<EntityClickRes> // or however you store it
 ) {
-    if target_entity.explorer.is_none() || !target_entity.is_changed() {
+    let Some(explorer_id) = target_entity.explorer else {
+        return;
+    };
+
+    if !target_entity.is_changed() && !snapshot.is_changed() && !explorer_status.is_changed() {
         return;
     }
 
     commands.entity(*list).despawn_children();
-    let explorer_id = target_entity.explorer.unwrap();
     let planet_id = explorer_status.map.get_current_planet(&explorer_id);
 
     let mut neighbors = Vec::new();
 
-    for edge in edges {
-        if edge.connects.0 == planet_id {
-            neighbors.push(edge.connects.1);
-        } else if edge.connects.1 == planet_id {
-            neighbors.push(edge.connects.0);
+    for &(a, b) in &snapshot.edges {
+        if a == planet_id {
+            neighbors.push(b);
+        } else if b == planet_id {
+            neighbors.push(a);
         }
     }
 
+    // dead planets can't be visited anymore
+    neighbors.retain(|id| planet_status.map.get_status(id) != types::Status::Dead);
     neighbors.sort_unstable();
     neighbors.dedup();
 
@@ -289,6 +338,25 @@ pub fn populate_dropdown(
     });
 }
 
+/// Small button of the filters of the log and of the message inspector,
+/// `label` marks the text so it can show the current value
+pub(crate) fn filter_button(text: impl Into<String>, label: impl Component, action: ButtonActions) -> impl Bundle {
+    (
+        Button,
+        BackgroundColor(Color::srgb(0.07, 0.30, 0.53)),
+        Node {
+            height: Val::Px(24.),
+            padding: UiRect::horizontal(Val::Px(6.)),
+            margin: UiRect::all(Val::Px(2.)),
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        BorderRadius::all(Val::Px(6.)),
+        action,
+        children![(Text::new(text), TextFont { font_size: 11., ..default() }, label)]
+    )
+}
+
 pub(crate) fn button_hover(
     mut interaction_query: Query<(&Interaction, &mut BackgroundColor), (Changed<Interaction>, With<Button>)>
 ) {
@@ -309,18 +377,37 @@ pub(crate) fn button_hover(
 }
 
 pub(crate) fn game_menu_action(
+    mut commands: Commands,
     mut action_query: Query<(&Interaction, &ButtonActions), (Changed<Interaction>, With<Button>)>,
     mut orchestrator: ResMut<OrchestratorResource>,
     mut state: ResMut<GameState>
 ) {
     for (&interaction, action) in &mut action_query {
+        // a finished game can only be restarted
+        if *state == GameState::Finished && !matches!(action, ButtonActions::Restart) {
+            continue;
+        }
+
         if interaction == Interaction::Pressed {
             match action {
                 ButtonActions::StartGame =>
-                    if state.set_if_neq(GameState::Playing) {
+                    if *state == GameState::WaitingStart {
+                        *state = GameState::Playing;
                         orchestrator.orchestrator.set_mode_auto();
                         info!("game started");
                     },
+                ButtonActions::Resume =>
+                    if matches!(*state, GameState::Paused | GameState::Override | GameState::Reviewing) {
+                        // manual actions switch the orchestrator to manual mode,
+                        // possibly before the game was paused
+                        orchestrator.orchestrator.set_mode_auto();
+                        *state = GameState::Playing;
+                        info!("game resumed");
+                    },
+                ButtonActions::Restart => {
+                    info!("restarting the game");
+                    commands.queue(restart_game);
+                }
                 ButtonActions::StopGame =>
                     if state.set_if_neq(GameState::Paused) {
                         println!("game should pause now...");
@@ -372,6 +459,42 @@ pub(crate) fn game_menu_action(
     }
 }
 
+/// Speed and single step buttons, available in live games and replays
+pub(crate) fn speed_action(
+    action_query: Query<(&Interaction, &ButtonActions), (Changed<Interaction>, With<Button>)>,
+    mut speed: ResMut<SimulationSpeed>,
+    mut step: ResMut<StepRequest>,
+    state: Res<GameState>
+) {
+    for (&interaction, action) in &action_query {
+        if interaction == Interaction::Pressed {
+            match action {
+                ButtonActions::SpeedDown => {
+                    speed.0 = (speed.0 / 2.).max(SimulationSpeed::MIN);
+                    info!("speed set to {}x", speed.0);
+                }
+                ButtonActions::SpeedUp => {
+                    speed.0 = (speed.0 * 2.).min(SimulationSpeed::MAX);
+                    info!("speed set to {}x", speed.0);
+                }
+                ButtonActions::StepOnce =>
+                    if matches!(*state, GameState::Paused | GameState::WaitingStart) {
+                        step.0 = true;
+                    } else {
+                        info!("pause the game before stepping");
+                    },
+                _ => {}
+            }
+        }
+    }
+}
+
+pub(crate) fn update_speed_label(speed: Res<SimulationSpeed>, mut label: Single<&mut Text, With<SpeedLabel>>) {
+    if speed.is_changed() {
+        label.0 = format!("Speed: {}x", speed.0);
+    }
+}
+
 pub fn update_planet_buttons_visibility(
     selected: Res<EntityClickRes>,
     mut query: Query<&mut Visibility, With<PlanetOnlyButton>>
@@ -443,6 +566,7 @@ pub(crate) fn manual_explorer_action(
     selected_entity: Res<EntityClickRes>,
     explorer_status: Res<ExplorerInfoRes>,
     planet_status: Res<PlanetInfoRes>,
+    mut rng: ResMut<SessionRng>,
     mut state: ResMut<GameState>
 ) {
     for (&interaction, action) in &mut action_query {
@@ -460,7 +584,7 @@ pub(crate) fn manual_explorer_action(
                         if let Some(planet) = planet_status.map.get_info(planet_id) {
                             let basic_resources = types::get_planet_basic_resources(planet.name);
                             if !basic_resources.is_empty() {
-                                let idx = rand::random::<i32>() as usize % basic_resources.len(); // get a random one
+                                let idx = rng.rng.random_range(0..basic_resources.len()); // get a random one
 
                                 orchestrator.orchestrator.schedule_manual_action(GenerateBasic {
                                     explorer_id,
@@ -480,7 +604,7 @@ pub(crate) fn manual_explorer_action(
                         if let Some(planet) = planet_status.map.get_info(planet_id) {
                             let basic_resources = types::get_planet_complex_resources(planet.name);
                             if !basic_resources.is_empty() {
-                                let idx = rand::random::<i32>() as usize % basic_resources.len(); // get a random one
+                                let idx = rng.rng.random_range(0..basic_resources.len()); // get a random one
 
                                 orchestrator.orchestrator.schedule_manual_action(GenerateComplex {
                                     explorer_id,
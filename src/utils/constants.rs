@@ -1,8 +1,47 @@
+use bevy::color::Color;
+
 pub(crate) const PLANET_SPRITE_NUM: usize = 7;
 pub(crate) const EXP_SPRITE_NUM: usize = 2;
 pub(crate) const PLANET_RAD: f32 = 50.;
 pub(crate) const CELESTIAL_RAD: f32 = PLANET_RAD / 2.;
 pub(crate) const GALAXY_RADIUS: f32 = 250.;
+pub(crate) const FORCE_ITERATIONS: usize = 300;
+pub(crate) const LAYOUT_ANIMATION_SECONDS: f32 = 1.;
 pub(crate) const EXPLORER_SIZE: f32 = 40.;
-pub(crate) const EXP_TOMMY_OFFSET: (f32, f32) = (25., 25.);
-pub(crate) const EXP_MATTIA_OFFSET: (f32, f32) = (-25., -25.);
+pub(crate) const EXPLORER_RING_RADIUS: f32 = 35.;
+pub(crate) const RESOURCE_POPUP_RISE: f32 = 40.;
+pub(crate) const RESOURCE_ICON_SIZE: f32 = 12.;
+pub(crate) const SIDE_MENU_WIDTH: f32 = 350.;
+/// Space left on both sides of the panels drawn between the side menus
+pub(crate) const CENTER_PANEL_MARGIN: f32 = SIDE_MENU_WIDTH + 20.;
+pub(crate) const MIN_ZOOM: f32 = 0.1;
+pub(crate) const MAX_ZOOM: f32 = 10.;
+pub(crate) const ZOOM_STEP: f32 = 1.1;
+pub(crate) const FOCUS_ZOOM: f32 = 0.4;
+pub(crate) const CAMERA_SMOOTHING: f32 = 8.;
+pub(crate) const DOUBLE_CLICK_SECONDS: f32 = 0.35;
+pub(crate) const TRAIL_SPACING: f32 = 8.;
+pub(crate) const TRAIL_DOT_SIZE: f32 = 4.;
+pub(crate) const TRAIL_SECONDS: f32 = 1.5;
+pub(crate) const MAX_MESSAGE_ROWS: usize = 200;
+pub(crate) const LOG_CAPACITY: usize = 500;
+pub(crate) const HISTORY_CAPACITY: usize = 2000;
+pub(crate) const STATS_WINDOW: usize = 60;
+pub(crate) const SUNRAY_BAR_COLOR: Color = Color::srgb(1., 0.85, 0.2);
+pub(crate) const ASTEROID_BAR_COLOR: Color = Color::srgb(0.9, 0.35, 0.25);
+pub(crate) const DEATH_SECONDS: f32 = 1.;
+pub(crate) const GHOST_COLOR: Color = Color::srgba(0.5, 0.5, 0.5, 0.35);
+pub(crate) const DASH_LENGTH: f32 = 6.;
+// outside of the sprite, even when it is enlarged by a click
+pub(crate) const ENERGY_RING_RADIUS: f32 = PLANET_RAD * 1.3;
+pub(crate) const ENERGY_SEGMENT_THICKNESS: f32 = 5.;
+pub(crate) const ENERGY_COLOR: Color = Color::srgb(1., 0.85, 0.2);
+pub(crate) const EMPTY_CELL_COLOR: Color = Color::srgba(0.3, 0.3, 0.3, 0.6);
+pub(crate) const ROCKET_BADGE_SIZE: f32 = 16.;
+pub(crate) const ROCKET_COLOR: Color = Color::srgb(1., 0.45, 0.1);
+// a tint can't desaturate a texture, darkening it to grey comes closest
+pub(crate) const PAUSED_TINT: Color = Color::srgb(0.45, 0.45, 0.45);
+pub(crate) const INACTIVE_EDGE_COLOR: Color = Color::srgba(1., 0.4, 0.3, 0.6);
+pub(crate) const STATUS_ICON_SIZE: f32 = 18.;
+pub(crate) const IMPACT_SECONDS: f32 = 0.5;
+pub(crate) const STATUS_ICON_COLOR: Color = Color::srgb(1., 0.9, 0.4);